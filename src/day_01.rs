@@ -62,8 +62,17 @@
 
 const INPUT: &str = include_str!("../input/day_01");
 
-pub fn run() {
-    let calibration_value_sum: u32 = INPUT.lines().filter_map(extract_calibration_value).sum();
+pub fn run(options: &[String]) {
+    // `--unicode` also accepts non-ASCII decimal digits, like Arabic-Indic or full-width digits
+    let mode = match options.iter().any(|option| option == "--unicode") {
+        true => DigitMode::Unicode,
+        false => DigitMode::Ascii,
+    };
+
+    let calibration_value_sum: u32 = INPUT
+        .lines()
+        .filter_map(|line| extract_calibration_value_with_mode(line, mode))
+        .sum();
 
     println!(
         "The sum of all the calibration values is: {}",
@@ -72,7 +81,7 @@ pub fn run() {
 
     let written_calibration_value_sum: u32 = INPUT
         .lines()
-        .filter_map(extract_written_calibration_value)
+        .filter_map(|line| extract_written_calibration_value_with_mode(line, mode))
         .sum();
 
     println!(
@@ -81,60 +90,103 @@ pub fn run() {
     );
 }
 
+/// Which characters are recognised as digits when reading the calibration document.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DigitMode {
+    /// Only the ASCII digits '0' to '9'.
+    Ascii,
+    /// Any Unicode decimal digit (general category Nd), e.g. Arabic-Indic or full-width digits.
+    Unicode,
+}
+
+/// The code points of the zero digit of every block of Unicode decimal digits as of Unicode 17.0,
+/// the version `char::is_numeric` follows. Each block consists of the ten contiguous digits zero
+/// to nine.
+const UNICODE_DIGIT_ZEROS: [u32; 77] = [
+    0x0030, 0x0660, 0x06F0, 0x07C0, 0x0966, 0x09E6, 0x0A66, 0x0AE6, 0x0B66, 0x0BE6, 0x0C66, 0x0CE6,
+    0x0D66, 0x0DE6, 0x0E50, 0x0ED0, 0x0F20, 0x1040, 0x1090, 0x17E0, 0x1810, 0x1946, 0x19D0, 0x1A80,
+    0x1A90, 0x1B50, 0x1BB0, 0x1C40, 0x1C50, 0xA620, 0xA8D0, 0xA900, 0xA9D0, 0xA9F0, 0xAA50, 0xABF0,
+    0xFF10, 0x104A0, 0x10D30, 0x10D40, 0x11066, 0x110F0, 0x11136, 0x111D0, 0x112F0, 0x11450,
+    0x114D0, 0x11650, 0x116C0, 0x116D0, 0x116DA, 0x11730, 0x118E0, 0x11950, 0x11BF0, 0x11C50,
+    0x11D50, 0x11DA0, 0x11DE0, 0x11F50, 0x16130, 0x16A60, 0x16AC0, 0x16B50, 0x16D70, 0x1CCF0,
+    0x1D7CE, 0x1D7D8, 0x1D7E2, 0x1D7EC, 0x1D7F6, 0x1E140, 0x1E2F0, 0x1E4F0, 0x1E5F1, 0x1E950,
+    0x1FBF0,
+];
+
+impl DigitMode {
+    fn to_digit(self, c: char) -> Option<u32> {
+        match self {
+            DigitMode::Ascii => c.to_digit(10),
+            DigitMode::Unicode => {
+                let code_point = c as u32;
+                // the table is sorted, so find the last block starting at or before the char
+                let zero = match UNICODE_DIGIT_ZEROS.binary_search(&code_point) {
+                    Ok(i) => UNICODE_DIGIT_ZEROS[i],
+                    Err(0) => return None,
+                    Err(i) => UNICODE_DIGIT_ZEROS[i - 1],
+                };
+                match code_point - zero {
+                    digit @ 0..=9 => Some(digit),
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
 fn extract_calibration_value(line: &str) -> Option<u32> {
-    let first = line.chars().filter(|c| c >= &'0' && c <= &'9').next();
-    let last = line.chars().rev().filter(|c| c >= &'0' && c <= &'9').next();
+    extract_calibration_value_with_mode(line, DigitMode::Ascii)
+}
+
+fn extract_calibration_value_with_mode(line: &str, mode: DigitMode) -> Option<u32> {
+    let first = line.chars().find_map(|c| mode.to_digit(c));
+    let last = line.chars().rev().find_map(|c| mode.to_digit(c));
 
-    Some(first?.to_digit(10)? * 10 + last?.to_digit(10)?)
+    Some(first? * 10 + last?)
 }
 
-fn convert_to_u32(i: usize, c: char, line: &str) -> Option<u32> {
-    if c >= '0' && c <= '9' {
-        return Some(c.to_digit(10)?);
-    }
-    if line[i..].starts_with("one") {
-        return Some(1);
-    }
-    if line[i..].starts_with("two") {
-        return Some(2);
-    }
-    if line[i..].starts_with("three") {
-        return Some(3);
-    }
-    if line[i..].starts_with("four") {
-        return Some(4);
-    }
-    if line[i..].starts_with("five") {
-        return Some(5);
-    }
-    if line[i..].starts_with("six") {
-        return Some(6);
-    }
-    if line[i..].starts_with("seven") {
-        return Some(7);
-    }
-    if line[i..].starts_with("eight") {
-        return Some(8);
-    }
-    if line[i..].starts_with("nine") {
-        return Some(9);
+const WRITTEN_DIGITS: [(&str, u32); 9] = [
+    ("one", 1),
+    ("two", 2),
+    ("three", 3),
+    ("four", 4),
+    ("five", 5),
+    ("six", 6),
+    ("seven", 7),
+    ("eight", 8),
+    ("nine", 9),
+];
+
+fn convert_to_u32(i: usize, c: char, line: &str, mode: DigitMode) -> Option<u32> {
+    if let Some(digit) = mode.to_digit(c) {
+        return Some(digit);
     }
-    None
+    // `i` comes from `char_indices`, so it is always on a char boundary, use `get` regardless so
+    // that a bad index can never panic on multibyte text
+    let rest = line.get(i..)?;
+    WRITTEN_DIGITS
+        .iter()
+        .find(|(word, _)| rest.starts_with(word))
+        .map(|&(_, digit)| digit)
 }
 
+#[cfg(test)]
 fn extract_written_calibration_value(line: &str) -> Option<u32> {
+    extract_written_calibration_value_with_mode(line, DigitMode::Ascii)
+}
+
+fn extract_written_calibration_value_with_mode(line: &str, mode: DigitMode) -> Option<u32> {
     // also check for the occurrence of substrings at every position
 
     let first = line
         .char_indices()
-        .filter_map(|(i, c)| convert_to_u32(i, c, line))
-        .next();
+        .find_map(|(i, c)| convert_to_u32(i, c, line, mode));
 
     let last = line
         .char_indices()
         .rev()
-        .filter_map(|(i, c)| convert_to_u32(i, c, line))
-        .next();
+        .find_map(|(i, c)| convert_to_u32(i, c, line, mode));
 
     Some(first? * 10 + last?)
 }
@@ -142,6 +194,7 @@ fn extract_written_calibration_value(line: &str) -> Option<u32> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::random::XorShift;
 
     #[test]
     fn test_extract_calibration_value_1() {
@@ -214,4 +267,103 @@ mod tests {
         // 7pqrstsixteen
         assert_eq!(extract_written_calibration_value("7pqrstsixteen"), Some(76));
     }
+
+    #[test]
+    fn test_extract_calibration_value_multibyte() {
+        assert_eq!(extract_calibration_value("é1ö€2ß"), Some(12));
+        assert_eq!(extract_calibration_value("日本語"), None);
+    }
+
+    #[test]
+    fn test_extract_calibration_value_ascii_ignores_unicode_digits() {
+        // Arabic-Indic 4 and full-width 7 are not ASCII digits
+        assert_eq!(extract_calibration_value("\u{664}a3b\u{FF17}"), Some(33));
+    }
+
+    #[test]
+    fn test_extract_calibration_value_unicode_digits() {
+        assert_eq!(
+            extract_calibration_value_with_mode("\u{664}a3b\u{FF17}", DigitMode::Unicode),
+            Some(47)
+        );
+        // Devanagari 9 and mathematical bold 0
+        assert_eq!(
+            extract_calibration_value_with_mode("x\u{96F}y\u{1D7CE}", DigitMode::Unicode),
+            Some(90)
+        );
+    }
+
+    #[test]
+    fn test_unicode_digit_zeros() {
+        // a newer version of Unicode can add blocks of digits, the table has to follow it
+        assert_eq!(char::UNICODE_VERSION, (17, 0, 0));
+        assert!(UNICODE_DIGIT_ZEROS
+            .windows(2)
+            .all(|pair| pair[0] + 10 <= pair[1]));
+        for zero in UNICODE_DIGIT_ZEROS {
+            for digit in 0..=9 {
+                let c = char::from_u32(zero + digit).unwrap();
+                assert!(c.is_numeric(), "U+{:04X} is not numeric", zero + digit);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unicode_digit_mode_rejects_other_numerics() {
+        // superscript two, vulgar fraction one half and roman numeral one are numeric, but not
+        // decimal digits
+        for c in ['\u{B2}', '\u{BD}', '\u{2160}', '\u{65F}', '\u{FF1A}'] {
+            assert_eq!(DigitMode::Unicode.to_digit(c), None);
+        }
+        for zero in UNICODE_DIGIT_ZEROS {
+            for digit in 0..=9 {
+                let c = char::from_u32(zero + digit).unwrap();
+                assert_eq!(DigitMode::Unicode.to_digit(c), Some(digit));
+            }
+        }
+    }
+
+    #[test]
+    fn test_written_extract_calibration_value_multibyte() {
+        assert_eq!(
+            extract_written_calibration_value("ñone€twö\u{1F600}nine"),
+            Some(19)
+        );
+        assert_eq!(
+            extract_written_calibration_value_with_mode("ｔwo\u{669}eight", DigitMode::Unicode),
+            Some(98)
+        );
+    }
+
+    #[test]
+    fn test_extract_calibration_value_arbitrary_utf8() {
+        // generate pseudo random lines of arbitrary chars, none of them should make the extraction
+        // panic and every value found should be two digits
+        const WRITTEN_LETTERS: &[u8] = b"onetwothreefourfivesixseveneightnine";
+        let mut rng = XorShift::new(0x2023_1201);
+
+        for _ in 0..2000 {
+            let length = rng.between(0, 39);
+            let line: String = (0..length)
+                .filter_map(|_| match rng.between(0, 3) {
+                    // bias towards ASCII so written digits show up every now and then
+                    0 => char::from_u32(rng.between(0, 0x7f) as u32),
+                    1 => Some(WRITTEN_LETTERS[rng.between(0, 35) as usize] as char),
+                    _ => char::from_u32(rng.between(0, 0x10_ffff) as u32),
+                })
+                .collect();
+
+            for mode in [DigitMode::Ascii, DigitMode::Unicode] {
+                for value in [
+                    extract_calibration_value_with_mode(&line, mode),
+                    extract_written_calibration_value_with_mode(&line, mode),
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    assert!(value <= 99, "{} from {:?}", value, line);
+                }
+            }
+        }
+    }
 }
@@ -74,23 +74,71 @@
 ///
 /// For each game, find the minimum set of cubes that must have been present. What is the sum of the
 /// power of these sets?
-use nom::branch::alt;
 use nom::bytes::complete::tag;
-use nom::character::complete::digit1;
-use nom::combinator::map_res;
+use nom::character::complete;
+use nom::character::complete::{alpha1, char, digit1};
+use nom::combinator::{all_consuming, map_res};
 use nom::multi::separated_list0;
 use nom::sequence::delimited;
 use nom::sequence::pair;
+use nom::sequence::separated_pair;
 use nom::IResult;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 use std::num::ParseIntError;
 
+use crate::random::XorShift;
+
+mod probability;
+
 const INPUT: &str = include_str!("../input/day_02");
 
-pub fn run() {
+/// The colours of the cubes in the puzzle.
+const PUZZLE_COLOURS: [&str; 3] = ["red", "green", "blue"];
+
+pub fn run(options: &[String]) {
+    // `--generate 100` prints that many random games instead of solving, `--seed` picks others
+    if let Some(amount) = crate::option_value(options, "--generate") {
+        let amount = amount.parse().expect("the amount should be a number");
+        let seed = crate::option_value(options, "--seed").map_or(2023, |seed| {
+            seed.parse().expect("the seed should be a number")
+        });
+        let mut rng = XorShift::new(seed);
+        for id in 1..=amount {
+            println!("{}", random_game(&mut rng, id, &PUZZLE_COLOURS));
+        }
+        return;
+    }
+
+    // the bag defaults to the one from the puzzle, `--bag red=12,green=13,blue=14` overrides it
+    let bag = match crate::option_value(options, "--bag") {
+        Some(bag) => CubeSet::parse_bag(bag).expect("parsing the bag failed").1,
+        None => CubeSet::from([("red", 12), ("green", 13), ("blue", 14)]),
+    };
+
+    // a colour listed twice in a round is rejected, unless `--duplicates sum` or
+    // `--duplicates max` says how to combine the amounts
+    let policy = match crate::option_value(options, "--duplicates") {
+        None | Some("reject") => DuplicatePolicy::Reject,
+        Some("sum") => DuplicatePolicy::Sum,
+        Some("max") => DuplicatePolicy::Max,
+        Some(other) => panic!("unknown duplicate policy: {}", other),
+    };
+
+    // `--validate` reports all the colours listed more than once in a round
+    if options.iter().any(|option| option == "--validate") {
+        for duplicate in find_duplicate_colours(INPUT) {
+            println!("{}", duplicate);
+        }
+    }
+
     let games: Vec<Game> = INPUT
         .lines()
-        .filter_map(|line| match Game::parse(line) {
-            Ok((_, game)) => Some(game),
+        .filter_map(|line| match Game::parse_draws(line) {
+            Ok((_, (id, rounds))) => Some(
+                Game::from_draws(id, &rounds, policy)
+                    .unwrap_or_else(|duplicate| panic!("{}", duplicate)),
+            ),
             _ => None,
         })
         .collect();
@@ -98,7 +146,7 @@ pub fn run() {
     let possible_game_id_sum: u32 = games
         .iter()
         .filter_map(|game| {
-            match game.legal_game() {
+            match game.legal_game(&bag) {
                 true => Some(game.id), // add legal games
                 false => None,         // ignore illegal games
             }
@@ -110,22 +158,90 @@ pub fn run() {
         possible_game_id_sum
     );
 
+    // `--report` lists for every game whether it's possible, and why not
+    if options.iter().any(|option| option == "--report") {
+        for game in games.iter() {
+            let violations = game.violations(&bag);
+            if violations.is_empty() {
+                println!(
+                    "Game {} is possible, with a likelihood of {:e}",
+                    game.id,
+                    probability::game_likelihood(&bag, game)
+                );
+                continue;
+            }
+            println!("Game {} is impossible:", game.id);
+            for violation in violations {
+                println!(
+                    "  round {} has {} {} more than the bag holds",
+                    violation.round, violation.excess, violation.colour
+                );
+            }
+        }
+    }
+
+    // the power multiplies the puzzle's colours and any other colour in the games, a colour that a
+    // game never shows counts as 0 cubes
+    let all_cubes = minimal_bag(&games);
+    let colours: BTreeSet<&str> = PUZZLE_COLOURS
+        .into_iter()
+        .chain(all_cubes.colours())
+        .collect();
+    let colours: Vec<&str> = colours.into_iter().collect();
     let minimal_power_set_sum: u32 = games
         .iter()
-        .map(|game| game.minimal_cube_set().power())
+        .map(|game| game.minimal_cube_set().power(&colours))
         .sum();
 
     println!(
         "The sum of the powers of the minimal cube sets is: {}",
         minimal_power_set_sum
-    )
+    );
+
+    println!(
+        "The smallest bag that makes every game possible is: {}",
+        minimal_bag(&games)
+    );
+
+    // `--budget 36` searches the bag of at most 36 cubes that makes the most games possible,
+    // `--objective id-sum` maximises the sum of their ids instead
+    if let Some(budget) = crate::option_value(options, "--budget") {
+        let budget = budget.parse().expect("the budget should be a number");
+        let objective = match crate::option_value(options, "--objective") {
+            None | Some("count") => Objective::Count,
+            Some("id-sum") => Objective::IdSum,
+            Some(other) => panic!("unknown objective: {}", other),
+        };
+        let (bag, score) = best_bag(&games, budget, objective);
+        println!(
+            "The best bag with at most {} cubes is: {} ({} cubes), scoring {}",
+            budget,
+            bag,
+            bag.total(),
+            score
+        );
+    }
+
+    // `--max-likelihood 100` estimates the bag of at most 100 cubes that most likely produced
+    // all the rounds, when every round is a handful drawn without replacement
+    if let Some(max_total) = crate::option_value(options, "--max-likelihood") {
+        let max_total = max_total.parse().expect("the maximum should be a number");
+        match probability::maximum_likelihood_bag(&games, max_total) {
+            Some((bag, _)) => println!(
+                "The most likely bag with at most {} cubes is: {}",
+                max_total, bag
+            ),
+            None => println!("No bag with at most {} cubes fits all games", max_total),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
+/// An amount of cubes per colour, colours that aren't in the set have no cubes.
+#[derive(Debug, Default, Clone, PartialEq)]
 struct CubeSet {
-    red: u32,
-    green: u32,
-    blue: u32,
+    // only colours with at least one cube are stored, so sets compare equal regardless of which
+    // colours were mentioned with 0 cubes
+    cubes: BTreeMap<String, u32>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -134,89 +250,389 @@ struct Game {
     rounds: Vec<CubeSet>,
 }
 
-impl CubeSet {
-    fn default() -> CubeSet {
-        CubeSet {
-            red: 0,
-            green: 0,
-            blue: 0,
+/// The cubes shown in a single round as listed in the input, an amount and a colour for each.
+type Draws<'a> = Vec<(u32, &'a str)>;
+
+/// What to do with a colour that is listed more than once in a single round, like `3 red, 4 red`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DuplicatePolicy {
+    /// Refuse the round.
+    Reject,
+    /// Add the amounts together.
+    Sum,
+    /// Keep the largest amount.
+    Max,
+}
+
+/// A colour that is listed more than once in a single round of a game.
+#[derive(Debug, PartialEq)]
+struct DuplicateColour {
+    game: u32,
+    /// The round in the game, starting at 1.
+    round: usize,
+    colour: String,
+    /// All the amounts listed for the colour, in order.
+    amounts: Vec<u32>,
+    /// Whether the amounts were added together, and their sum doesn't fit in a `u32`.
+    overflowed: bool,
+}
+
+/// A colour in a round of a game that shows more cubes than the bag holds.
+#[derive(Debug, PartialEq)]
+struct Violation {
+    /// The round in the game, starting at 1.
+    round: usize,
+    colour: String,
+    /// How many more cubes were shown than the bag holds.
+    excess: u32,
+}
+
+impl<const N: usize> From<[(&str, u32); N]> for CubeSet {
+    fn from(cubes: [(&str, u32); N]) -> CubeSet {
+        let mut cube_set = CubeSet::default();
+        for (colour, amount) in cubes {
+            cube_set.set(colour, amount);
         }
+        cube_set
     }
+}
 
-    fn parse(input: &str) -> IResult<&str, CubeSet> {
-        let extract_cube_set = separated_list0(
-            tag(", "),
-            pair(digit1, alt((tag(" red"), tag(" green"), tag(" blue")))),
-        );
+impl CubeSet {
+    fn parse_draws(input: &str) -> IResult<&str, Draws<'_>> {
+        separated_list0(tag(", "), separated_pair(complete::u32, char(' '), alpha1))(input)
+    }
 
-        fn convert_to_cube_set(cubes: Vec<(&str, &str)>) -> Result<CubeSet, ParseIntError> {
-            let mut cube_set = CubeSet::default();
+    /// Combine the draws of a round into a set, on rejection or when the summed amounts don't fit the
+    /// duplicated colour is returned.
+    fn from_draws<'a>(
+        draws: &[(u32, &'a str)],
+        policy: DuplicatePolicy,
+    ) -> Result<CubeSet, &'a str> {
+        let mut cube_set = CubeSet::default();
+        // keep track of the listed colours separately, a colour listed with 0 cubes isn't stored
+        let mut listed = BTreeSet::new();
 
-            for (a, colour) in cubes {
-                let amount = a.parse()?;
+        for &(amount, colour) in draws {
+            if listed.insert(colour) {
+                cube_set.set(colour, amount);
+                continue;
+            }
+            match policy {
+                DuplicatePolicy::Reject => return Err(colour),
+                DuplicatePolicy::Sum => match cube_set.get(colour).checked_add(amount) {
+                    Some(sum) => cube_set.set(colour, sum),
+                    None => return Err(colour),
+                },
+                DuplicatePolicy::Max => cube_set.set(colour, cube_set.get(colour).max(amount)),
+            }
+        }
 
-                if colour == " red" {
-                    cube_set.red = amount;
-                }
-                if colour == " green" {
-                    cube_set.green = amount;
-                }
-                if colour == " blue" {
-                    cube_set.blue = amount;
-                }
+        Ok(cube_set)
+    }
+
+    /// Parse a bag description like `red=12,green=13,blue=14`, colours left out hold no cubes.
+    fn parse_bag(input: &str) -> IResult<&str, CubeSet> {
+        let extract_bag = separated_list0(char(','), separated_pair(alpha1, char('='), digit1));
+
+        fn convert_to_bag(cubes: Vec<(&str, &str)>) -> Result<CubeSet, ParseIntError> {
+            let mut bag = CubeSet::default();
+
+            for (colour, a) in cubes {
+                bag.set(colour, a.parse()?);
             }
 
-            return Ok(cube_set);
+            Ok(bag)
         }
 
-        map_res(extract_cube_set, convert_to_cube_set)(input)
+        all_consuming(map_res(extract_bag, convert_to_bag))(input)
+    }
+
+    fn get(&self, colour: &str) -> u32 {
+        self.cubes.get(colour).copied().unwrap_or(0)
+    }
+
+    fn set(&mut self, colour: &str, amount: u32) {
+        match amount {
+            0 => self.cubes.remove(colour),
+            _ => self.cubes.insert(colour.to_string(), amount),
+        };
+    }
+
+    /// All the colours with at least one cube in the set.
+    fn colours(&self) -> impl Iterator<Item = &str> {
+        self.cubes.keys().map(String::as_str)
+    }
+
+    /// Whether every colour in the other set fits in this set.
+    fn contains(&self, other: &CubeSet) -> bool {
+        other
+            .colours()
+            .all(|colour| other.get(colour) <= self.get(colour))
+    }
+
+    /// Grow the set so it contains the other set as well.
+    fn extend_to(&mut self, other: &CubeSet) {
+        for colour in other.colours() {
+            if other.get(colour) > self.get(colour) {
+                self.set(colour, other.get(colour));
+            }
+        }
     }
 
-    fn power(&self) -> u32 {
-        self.red * self.green * self.blue
+    /// The amount of cubes in the set, as a `u64` so it fits even when every colour holds close to
+    /// `u32::MAX` cubes.
+    fn total(&self) -> u64 {
+        self.cubes.values().map(|&amount| u64::from(amount)).sum()
+    }
+
+    /// The amounts of the colours multiplied together, a colour that isn't in the set makes the
+    /// power 0.
+    fn power(&self, colours: &[&str]) -> u32 {
+        colours.iter().map(|colour| self.get(colour)).product()
     }
 }
 
 impl Game {
+    #[cfg(test)]
     fn parse(input: &str) -> IResult<&str, Game> {
-        let extract_values = pair(
-            delimited(tag("Game "), digit1, tag(": ")),
-            separated_list0(tag("; "), CubeSet::parse),
-        );
+        map_res(Game::parse_draws, |(id, rounds)| {
+            Game::from_draws(id, &rounds, DuplicatePolicy::Reject)
+        })(input)
+    }
 
-        fn convert_to_game((number, rounds): (&str, Vec<CubeSet>)) -> Result<Game, ParseIntError> {
-            Ok(Game {
-                id: number.parse()?,
-                rounds,
+    fn parse_draws(input: &str) -> IResult<&str, (u32, Vec<Draws<'_>>)> {
+        pair(
+            delimited(tag("Game "), complete::u32, tag(": ")),
+            separated_list0(tag("; "), CubeSet::parse_draws),
+        )(input)
+    }
+
+    fn from_draws(
+        id: u32,
+        rounds: &[Draws],
+        policy: DuplicatePolicy,
+    ) -> Result<Game, DuplicateColour> {
+        let rounds = rounds
+            .iter()
+            .enumerate()
+            .map(|(i, draws)| {
+                CubeSet::from_draws(draws, policy).map_err(|colour| DuplicateColour {
+                    // summing only fails when the sum doesn't fit
+                    overflowed: policy == DuplicatePolicy::Sum,
+                    ..DuplicateColour::new(id, i + 1, colour, draws)
+                })
             })
-        }
+            .collect::<Result<_, _>>()?;
 
-        map_res(extract_values, convert_to_game)(input)
+        Ok(Game { id, rounds })
     }
 
-    fn legal_game(&self) -> bool {
-        // return true if none of the rounds has amounts above the expected amounts
-        self.rounds
-            .iter()
-            .find(|round| round.red > 12 || round.green > 13 || round.blue > 14)
-            .is_none()
+    fn legal_game(&self, bag: &CubeSet) -> bool {
+        // return true if none of the rounds has amounts above the amounts in the bag
+        self.rounds.iter().all(|round| bag.contains(round))
+    }
+
+    fn violations(&self, bag: &CubeSet) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for (i, round) in self.rounds.iter().enumerate() {
+            for colour in round.colours() {
+                let (shown, held) = (round.get(colour), bag.get(colour));
+                if shown > held {
+                    violations.push(Violation {
+                        round: i + 1,
+                        colour: colour.to_string(),
+                        excess: shown - held,
+                    });
+                }
+            }
+        }
+        violations
     }
 
     fn minimal_cube_set(&self) -> CubeSet {
         let mut minimal = CubeSet::default();
         for round in self.rounds.iter() {
-            if round.red > minimal.red {
-                minimal.red = round.red;
+            minimal.extend_to(round);
+        }
+        minimal
+    }
+}
+
+impl DuplicateColour {
+    fn new(game: u32, round: usize, colour: &str, draws: &[(u32, &str)]) -> DuplicateColour {
+        DuplicateColour {
+            game,
+            round,
+            colour: colour.to_string(),
+            amounts: draws
+                .iter()
+                .filter(|(_, c)| *c == colour)
+                .map(|&(amount, _)| amount)
+                .collect(),
+            overflowed: false,
+        }
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rounds: Vec<String> = self.rounds.iter().map(CubeSet::to_string).collect();
+        write!(f, "Game {}: {}", self.id, rounds.join("; "))
+    }
+}
+
+/// Writes the cubes like `6 blue, 2 green, 1 red`. A set doesn't remember the order its colours
+/// were listed in, so they're always written in alphabetical order, and a colour with 0 cubes isn't
+/// in the set so it isn't written at all. Parsing the text gives back an equal set.
+impl fmt::Display for CubeSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cubes: Vec<String> = self
+            .cubes
+            .iter()
+            .map(|(colour, amount)| format!("{} {}", amount, colour))
+            .collect();
+        write!(f, "{}", cubes.join(", "))
+    }
+}
+
+impl fmt::Display for DuplicateColour {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let amounts: Vec<String> = self.amounts.iter().map(u32::to_string).collect();
+        write!(
+            f,
+            "Game {}, round {} lists {} more than once: {}",
+            self.game,
+            self.round,
+            self.colour,
+            amounts.join(", ")
+        )?;
+        match self.overflowed {
+            true => write!(f, ", too many to add up"),
+            false => Ok(()),
+        }
+    }
+}
+
+/// Generate a game with 1 to 6 rounds, each showing 1 to 20 cubes of some of the colours. Every
+/// round shows at least one colour, as a round without cubes can't be written down.
+fn random_game(rng: &mut XorShift, id: u32, colours: &[&str]) -> Game {
+    let rounds = (0..rng.between(1, 6))
+        .map(|_| {
+            let mut round = CubeSet::default();
+            while round.colours().next().is_none() {
+                for colour in colours {
+                    if rng.between(0, 1) == 1 {
+                        round.set(colour, rng.between(1, 20) as u32);
+                    }
+                }
             }
-            if round.green > minimal.green {
-                minimal.green = round.green;
+            round
+        })
+        .collect();
+
+    Game { id, rounds }
+}
+
+/// Find every colour that is listed more than once in a round, for all the games in the input.
+fn find_duplicate_colours(input: &str) -> Vec<DuplicateColour> {
+    let mut duplicates = Vec::new();
+    for line in input.lines() {
+        let Ok((_, (id, rounds))) = Game::parse_draws(line) else {
+            continue;
+        };
+        for (i, draws) in rounds.iter().enumerate() {
+            let mut listed = BTreeSet::new();
+            let mut reported = BTreeSet::new();
+            for &(_, colour) in draws {
+                if !listed.insert(colour) && reported.insert(colour) {
+                    duplicates.push(DuplicateColour::new(id, i + 1, colour, draws));
+                }
             }
-            if round.blue > minimal.blue {
-                minimal.blue = round.blue;
+        }
+    }
+    duplicates
+}
+
+/// The smallest bag that makes all the given games possible, any bag containing it will make
+/// them possible as well. Pass a subset of the games to find the bags for just those games.
+fn minimal_bag<'a>(games: impl IntoIterator<Item = &'a Game>) -> CubeSet {
+    let mut bag = CubeSet::default();
+    for game in games {
+        bag.extend_to(&game.minimal_cube_set());
+    }
+    bag
+}
+
+/// What a bag is scored on when searching for the best bag.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Objective {
+    /// The number of possible games.
+    Count,
+    /// The sum of the ids of the possible games.
+    IdSum,
+}
+
+/// Find the bag with at most `budget` cubes in total that scores best on the objective, together
+/// with its score.
+fn best_bag(games: &[Game], budget: u32, objective: Objective) -> (CubeSet, u32) {
+    let minimal_sets: Vec<(u32, CubeSet)> = games
+        .iter()
+        .map(|game| match objective {
+            Objective::Count => (1, game.minimal_cube_set()),
+            Objective::IdSum => (game.id, game.minimal_cube_set()),
+        })
+        .collect();
+
+    // a best bag can always be shrunk until every colour holds an amount that some game needs,
+    // so only those amounts (or none at all) have to be tried for each colour
+    let mut candidates: BTreeMap<&str, BTreeSet<u32>> = BTreeMap::new();
+    for (_, set) in minimal_sets.iter() {
+        for colour in set.colours() {
+            candidates
+                .entry(colour)
+                .or_insert_with(|| BTreeSet::from([0]))
+                .insert(set.get(colour));
+        }
+    }
+    let candidates: Vec<(&str, BTreeSet<u32>)> = candidates.into_iter().collect();
+
+    fn search(
+        candidates: &[(&str, BTreeSet<u32>)],
+        budget: u32,
+        bag: &mut CubeSet,
+        minimal_sets: &[(u32, CubeSet)],
+        best: &mut (CubeSet, u32),
+    ) {
+        let Some(((colour, amounts), rest)) = candidates.split_first() else {
+            // every colour has an amount, score the bag
+            let score = minimal_sets
+                .iter()
+                .filter(|(_, set)| bag.contains(set))
+                .map(|(weight, _)| weight)
+                .sum();
+            if score > best.1 {
+                *best = (bag.clone(), score);
             }
+            return;
+        };
+
+        // the amounts are sorted, so stop as soon as one doesn't fit in the budget anymore
+        for &amount in amounts.iter().take_while(|&&amount| amount <= budget) {
+            bag.set(colour, amount);
+            search(rest, budget - amount, bag, minimal_sets, best);
         }
-        minimal
+        bag.set(colour, 0);
     }
+
+    let mut best = (CubeSet::default(), 0);
+    search(
+        &candidates,
+        budget,
+        &mut CubeSet::default(),
+        &minimal_sets,
+        &mut best,
+    );
+    best
 }
 
 #[cfg(test)]
@@ -230,21 +646,9 @@ mod tests {
         let expected_game = Game {
             id: 1,
             rounds: vec![
-                CubeSet {
-                    red: 4,
-                    green: 0,
-                    blue: 3,
-                },
-                CubeSet {
-                    red: 1,
-                    green: 2,
-                    blue: 6,
-                },
-                CubeSet {
-                    red: 0,
-                    green: 2,
-                    blue: 0,
-                },
+                CubeSet::from([("red", 4), ("green", 0), ("blue", 3)]),
+                CubeSet::from([("red", 1), ("green", 2), ("blue", 6)]),
+                CubeSet::from([("red", 0), ("green", 2), ("blue", 0)]),
             ],
         };
 
@@ -257,21 +661,9 @@ mod tests {
         let expected_game = Game {
             id: 2,
             rounds: vec![
-                CubeSet {
-                    red: 0,
-                    green: 2,
-                    blue: 1,
-                },
-                CubeSet {
-                    red: 1,
-                    green: 3,
-                    blue: 4,
-                },
-                CubeSet {
-                    red: 0,
-                    green: 1,
-                    blue: 1,
-                },
+                CubeSet::from([("red", 0), ("green", 2), ("blue", 1)]),
+                CubeSet::from([("red", 1), ("green", 3), ("blue", 4)]),
+                CubeSet::from([("red", 0), ("green", 1), ("blue", 1)]),
             ],
         };
 
@@ -284,21 +676,9 @@ mod tests {
         let expected_game = Game {
             id: 3,
             rounds: vec![
-                CubeSet {
-                    red: 20,
-                    green: 8,
-                    blue: 6,
-                },
-                CubeSet {
-                    red: 4,
-                    green: 13,
-                    blue: 5,
-                },
-                CubeSet {
-                    red: 1,
-                    green: 5,
-                    blue: 0,
-                },
+                CubeSet::from([("red", 20), ("green", 8), ("blue", 6)]),
+                CubeSet::from([("red", 4), ("green", 13), ("blue", 5)]),
+                CubeSet::from([("red", 1), ("green", 5), ("blue", 0)]),
             ],
         };
 
@@ -311,21 +691,9 @@ mod tests {
         let expected_game = Game {
             id: 4,
             rounds: vec![
-                CubeSet {
-                    red: 3,
-                    green: 1,
-                    blue: 6,
-                },
-                CubeSet {
-                    red: 6,
-                    green: 3,
-                    blue: 0,
-                },
-                CubeSet {
-                    red: 14,
-                    green: 3,
-                    blue: 15,
-                },
+                CubeSet::from([("red", 3), ("green", 1), ("blue", 6)]),
+                CubeSet::from([("red", 6), ("green", 3), ("blue", 0)]),
+                CubeSet::from([("red", 14), ("green", 3), ("blue", 15)]),
             ],
         };
 
@@ -338,16 +706,8 @@ mod tests {
         let expected_game = Game {
             id: 5,
             rounds: vec![
-                CubeSet {
-                    red: 6,
-                    green: 3,
-                    blue: 1,
-                },
-                CubeSet {
-                    red: 1,
-                    green: 2,
-                    blue: 2,
-                },
+                CubeSet::from([("red", 6), ("green", 3), ("blue", 1)]),
+                CubeSet::from([("red", 1), ("green", 2), ("blue", 2)]),
             ],
         };
 
@@ -359,25 +719,13 @@ mod tests {
         let game = Game {
             id: 1,
             rounds: vec![
-                CubeSet {
-                    red: 4,
-                    green: 0,
-                    blue: 3,
-                },
-                CubeSet {
-                    red: 1,
-                    green: 2,
-                    blue: 6,
-                },
-                CubeSet {
-                    red: 0,
-                    green: 2,
-                    blue: 0,
-                },
+                CubeSet::from([("red", 4), ("green", 0), ("blue", 3)]),
+                CubeSet::from([("red", 1), ("green", 2), ("blue", 6)]),
+                CubeSet::from([("red", 0), ("green", 2), ("blue", 0)]),
             ],
         };
 
-        assert_eq!(game.minimal_cube_set().power(), 48);
+        assert_eq!(game.minimal_cube_set().power(&PUZZLE_COLOURS), 48);
     }
 
     #[test]
@@ -385,25 +733,13 @@ mod tests {
         let game = Game {
             id: 2,
             rounds: vec![
-                CubeSet {
-                    red: 0,
-                    green: 2,
-                    blue: 1,
-                },
-                CubeSet {
-                    red: 1,
-                    green: 3,
-                    blue: 4,
-                },
-                CubeSet {
-                    red: 0,
-                    green: 1,
-                    blue: 1,
-                },
+                CubeSet::from([("red", 0), ("green", 2), ("blue", 1)]),
+                CubeSet::from([("red", 1), ("green", 3), ("blue", 4)]),
+                CubeSet::from([("red", 0), ("green", 1), ("blue", 1)]),
             ],
         };
 
-        assert_eq!(game.minimal_cube_set().power(), 12);
+        assert_eq!(game.minimal_cube_set().power(&PUZZLE_COLOURS), 12);
     }
 
     #[test]
@@ -411,25 +747,13 @@ mod tests {
         let game = Game {
             id: 3,
             rounds: vec![
-                CubeSet {
-                    red: 20,
-                    green: 8,
-                    blue: 6,
-                },
-                CubeSet {
-                    red: 4,
-                    green: 13,
-                    blue: 5,
-                },
-                CubeSet {
-                    red: 1,
-                    green: 5,
-                    blue: 0,
-                },
+                CubeSet::from([("red", 20), ("green", 8), ("blue", 6)]),
+                CubeSet::from([("red", 4), ("green", 13), ("blue", 5)]),
+                CubeSet::from([("red", 1), ("green", 5), ("blue", 0)]),
             ],
         };
 
-        assert_eq!(game.minimal_cube_set().power(), 1560);
+        assert_eq!(game.minimal_cube_set().power(&PUZZLE_COLOURS), 1560);
     }
 
     #[test]
@@ -437,25 +761,13 @@ mod tests {
         let game = Game {
             id: 4,
             rounds: vec![
-                CubeSet {
-                    red: 3,
-                    green: 1,
-                    blue: 6,
-                },
-                CubeSet {
-                    red: 6,
-                    green: 3,
-                    blue: 0,
-                },
-                CubeSet {
-                    red: 14,
-                    green: 3,
-                    blue: 15,
-                },
+                CubeSet::from([("red", 3), ("green", 1), ("blue", 6)]),
+                CubeSet::from([("red", 6), ("green", 3), ("blue", 0)]),
+                CubeSet::from([("red", 14), ("green", 3), ("blue", 15)]),
             ],
         };
 
-        assert_eq!(game.minimal_cube_set().power(), 630);
+        assert_eq!(game.minimal_cube_set().power(&PUZZLE_COLOURS), 630);
     }
 
     #[test]
@@ -463,19 +775,333 @@ mod tests {
         let game = Game {
             id: 5,
             rounds: vec![
-                CubeSet {
-                    red: 6,
-                    green: 3,
-                    blue: 1,
-                },
-                CubeSet {
-                    red: 1,
-                    green: 2,
-                    blue: 2,
-                },
+                CubeSet::from([("red", 6), ("green", 3), ("blue", 1)]),
+                CubeSet::from([("red", 1), ("green", 2), ("blue", 2)]),
             ],
         };
 
-        assert_eq!(game.minimal_cube_set().power(), 36);
+        assert_eq!(game.minimal_cube_set().power(&PUZZLE_COLOURS), 36);
+    }
+
+    #[test]
+    fn test_game_cube_powers_missing_colour() {
+        let (_, game) = Game::parse("Game 1: 3 blue").unwrap();
+
+        // no red or green cubes were shown, the minimal set has none of them
+        assert_eq!(game.minimal_cube_set().power(&PUZZLE_COLOURS), 0);
+        assert_eq!(game.minimal_cube_set().power(&["blue"]), 3);
+    }
+
+    fn puzzle_bag() -> CubeSet {
+        CubeSet::from([("red", 12), ("green", 13), ("blue", 14)])
+    }
+
+    #[test]
+    fn test_parse_bag() {
+        assert_eq!(
+            CubeSet::parse_bag("red=12,green=13,blue=14"),
+            Ok(("", puzzle_bag()))
+        );
+        assert_eq!(
+            CubeSet::parse_bag("blue=2,red=1"),
+            Ok(("", CubeSet::from([("red", 1), ("green", 0), ("blue", 2)])))
+        );
+        assert!(CubeSet::parse_bag("red=12,yellow").is_err());
+    }
+
+    #[test]
+    fn test_legal_game() {
+        let line = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green";
+        let (_, game) = Game::parse(line).unwrap();
+        assert!(game.legal_game(&puzzle_bag()));
+
+        let small_bag = CubeSet::from([("red", 4), ("green", 2), ("blue", 5)]);
+        assert!(!game.legal_game(&small_bag));
+    }
+
+    #[test]
+    fn test_violations() {
+        let (_, game) =
+            Game::parse("Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red")
+                .unwrap();
+
+        assert_eq!(
+            game.violations(&puzzle_bag()),
+            vec![Violation {
+                round: 1,
+                colour: "red".to_string(),
+                excess: 8,
+            }]
+        );
+
+        let small_bag = CubeSet::from([("red", 4), ("green", 8), ("blue", 6)]);
+        assert_eq!(
+            game.violations(&small_bag),
+            vec![
+                Violation {
+                    round: 1,
+                    colour: "red".to_string(),
+                    excess: 16,
+                },
+                Violation {
+                    round: 2,
+                    colour: "green".to_string(),
+                    excess: 5,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cube_set_parse_any_colour() {
+        let (_, game) = Game::parse("Game 6: 3 yellow, 4 red, 0 blue").unwrap();
+        assert_eq!(
+            game.rounds,
+            vec![CubeSet::from([("yellow", 3), ("red", 4)])]
+        );
+    }
+
+    #[test]
+    fn test_cube_set_total() {
+        let bag = CubeSet::from([
+            ("red", 2000000000),
+            ("green", 2000000000),
+            ("blue", 2000000000),
+        ]);
+
+        assert_eq!(bag.total(), 6000000000);
+        assert_eq!(CubeSet::default().total(), 0);
+    }
+
+    #[test]
+    fn test_game_with_other_colours() {
+        let (_, game) =
+            Game::parse("Game 7: 3 yellow, 4 red; 1 red, 2 purple; 5 yellow, 2 green").unwrap();
+
+        assert_eq!(
+            game.minimal_cube_set(),
+            CubeSet::from([("yellow", 5), ("red", 4), ("purple", 2), ("green", 2)])
+        );
+        assert_eq!(
+            game.minimal_cube_set()
+                .power(&["yellow", "red", "purple", "green"]),
+            80
+        );
+        // there's no blue in the game
+        assert_eq!(game.minimal_cube_set().power(&PUZZLE_COLOURS), 0);
+
+        let bag = CubeSet::from([("red", 12), ("green", 13), ("blue", 14), ("yellow", 4)]);
+        assert!(!game.legal_game(&bag));
+        assert_eq!(
+            game.violations(&bag),
+            vec![
+                Violation {
+                    round: 2,
+                    colour: "purple".to_string(),
+                    excess: 2,
+                },
+                Violation {
+                    round: 3,
+                    colour: "yellow".to_string(),
+                    excess: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_colour_rejected() {
+        let line = "Game 8: 1 blue; 3 red, 2 green, 4 red";
+
+        assert!(Game::parse(line).is_err());
+
+        let (_, (id, rounds)) = Game::parse_draws(line).unwrap();
+        assert_eq!(
+            Game::from_draws(id, &rounds, DuplicatePolicy::Reject),
+            Err(DuplicateColour {
+                game: 8,
+                round: 2,
+                colour: "red".to_string(),
+                amounts: vec![3, 4],
+                overflowed: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_duplicate_colour_sum_overflow() {
+        let line = "Game 9: 4000000000 red, 2 green, 400000000 red";
+
+        let (_, (id, rounds)) = Game::parse_draws(line).unwrap();
+        let overflow = Game::from_draws(id, &rounds, DuplicatePolicy::Sum).unwrap_err();
+        assert_eq!(
+            overflow,
+            DuplicateColour {
+                game: 9,
+                round: 1,
+                colour: "red".to_string(),
+                amounts: vec![4000000000, 400000000],
+                overflowed: true,
+            }
+        );
+        assert_eq!(
+            overflow.to_string(),
+            "Game 9, round 1 lists red more than once: 4000000000, 400000000, too many to add up"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_colour_sum_and_max() {
+        let line = "Game 8: 1 blue; 3 red, 2 green, 4 red";
+
+        let (_, (id, rounds)) = Game::parse_draws(line).unwrap();
+
+        let summed = Game::from_draws(id, &rounds, DuplicatePolicy::Sum).unwrap();
+        assert_eq!(
+            summed.rounds,
+            vec![
+                CubeSet::from([("blue", 1)]),
+                CubeSet::from([("red", 7), ("green", 2)])
+            ]
+        );
+
+        let max = Game::from_draws(id, &rounds, DuplicatePolicy::Max).unwrap();
+        assert_eq!(
+            max.rounds,
+            vec![
+                CubeSet::from([("blue", 1)]),
+                CubeSet::from([("red", 4), ("green", 2)])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_duplicate_colours() {
+        let input = "\
+            Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green\n\
+            Game 2: 1 blue, 2 blue, 1 blue; 3 green, 0 red, 1 red\n\
+            Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red";
+
+        let duplicates = find_duplicate_colours(input);
+        assert_eq!(
+            duplicates,
+            vec![
+                DuplicateColour {
+                    game: 2,
+                    round: 1,
+                    colour: "blue".to_string(),
+                    amounts: vec![1, 2, 1],
+                    overflowed: false,
+                },
+                DuplicateColour {
+                    game: 2,
+                    round: 2,
+                    colour: "red".to_string(),
+                    amounts: vec![0, 1],
+                    overflowed: false,
+                },
+            ]
+        );
+        assert_eq!(
+            duplicates[0].to_string(),
+            "Game 2, round 1 lists blue more than once: 1, 2, 1"
+        );
+    }
+
+    fn example_games() -> Vec<Game> {
+        let input = "\
+            Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green\n\
+            Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue\n\
+            Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red\n\
+            Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red\n\
+            Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
+
+        input
+            .lines()
+            .map(|line| Game::parse(line).unwrap().1)
+            .collect()
+    }
+
+    #[test]
+    fn test_minimal_bag() {
+        let games = example_games();
+
+        let bag = minimal_bag(&games);
+        assert_eq!(
+            bag,
+            CubeSet::from([("red", 20), ("green", 13), ("blue", 15)])
+        );
+        assert!(games.iter().all(|game| game.legal_game(&bag)));
+    }
+
+    #[test]
+    fn test_minimal_bag_for_subset() {
+        let games = example_games();
+        let target = [1, 2, 5];
+
+        let bag = minimal_bag(games.iter().filter(|game| target.contains(&game.id)));
+        assert_eq!(bag, CubeSet::from([("red", 6), ("green", 3), ("blue", 6)]));
+
+        let possible: Vec<u32> = games
+            .iter()
+            .filter(|game| game.legal_game(&bag))
+            .map(|game| game.id)
+            .collect();
+        assert_eq!(possible, target);
+    }
+
+    #[test]
+    fn test_best_bag_count() {
+        let games = example_games();
+
+        let (bag, score) = best_bag(&games, 15, Objective::Count);
+        assert_eq!(score, 3);
+        assert_eq!(bag, CubeSet::from([("red", 6), ("green", 3), ("blue", 6)]));
+
+        let (bag, score) = best_bag(&games, 12, Objective::Count);
+        assert_eq!(score, 1);
+        assert!(bag.total() <= 12);
+    }
+
+    #[test]
+    fn test_best_bag_id_sum() {
+        let games = example_games();
+
+        let (bag, score) = best_bag(&games, 12, Objective::IdSum);
+        assert_eq!(score, 5);
+        assert_eq!(bag, CubeSet::from([("red", 6), ("green", 3), ("blue", 2)]));
+
+        let (bag, score) = best_bag(&games, 48, Objective::IdSum);
+        assert_eq!(score, 15);
+        assert_eq!(bag, minimal_bag(&games));
+    }
+
+    #[test]
+    fn test_game_display() {
+        let (_, game) =
+            Game::parse("Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green").unwrap();
+
+        // the colours come back in alphabetical order, not in the order they were listed
+        assert_eq!(
+            game.to_string(),
+            "Game 1: 3 blue, 4 red; 6 blue, 2 green, 1 red; 2 green"
+        );
+    }
+
+    #[test]
+    fn test_game_display_round_trip() {
+        let mut rng = XorShift::new(2);
+        let colours = ["red", "green", "blue", "yellow", "purple"];
+
+        for id in 1..=500 {
+            let game = random_game(&mut rng, id, &colours);
+            let line = game.to_string();
+
+            // the colours are always written in alphabetical order, so writing the parsed game
+            // again gives back the same text
+            let (rest, parsed) = Game::parse(&line).unwrap();
+            assert_eq!((rest, &parsed), ("", &game), "{}", line);
+            assert_eq!(parsed.to_string(), line);
+        }
     }
 }
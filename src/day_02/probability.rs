@@ -0,0 +1,161 @@
+/// A probabilistic model of the game: every round is a handful of cubes drawn from the bag without
+/// replacement (and put back before the next round). Given how many cubes are in a handful, every
+/// combination of that many cubes is equally likely, so the chance of a round follows the
+/// multivariate hypergeometric distribution.
+use super::{minimal_bag, CubeSet, Game};
+
+/// The logarithm of n choose k, in O(k) steps so it doesn't depend on how big n is. Rounds only
+/// draw a handful of cubes, so k stays small even when the bag is huge.
+fn ln_choose(n: u64, k: u64) -> f64 {
+    // n choose k is the same as n choose n - k, take whichever is fewer steps
+    let k = k.min(n - k);
+    (0..k)
+        .map(|i| ((n - i) as f64).ln() - ((i + 1) as f64).ln())
+        .sum()
+}
+
+/// The logarithm of the chance to draw exactly the round from the bag.
+fn ln_round_probability(bag: &CubeSet, round: &CubeSet) -> f64 {
+    if !bag.contains(round) {
+        return f64::NEG_INFINITY;
+    }
+    // every way to pick the shown cubes of each colour, out of every way to pick a handful
+    let ways: f64 = round
+        .colours()
+        .map(|colour| ln_choose(bag.get(colour).into(), round.get(colour).into()))
+        .sum();
+    ways - ln_choose(bag.total(), round.total())
+}
+
+fn ln_likelihood(bag: &CubeSet, games: &[Game]) -> f64 {
+    games
+        .iter()
+        .flat_map(|game| game.rounds.iter())
+        .map(|round| ln_round_probability(bag, round))
+        .sum()
+}
+
+/// The chance of drawing exactly the cubes of the round from the bag.
+pub(super) fn round_probability(bag: &CubeSet, round: &CubeSet) -> f64 {
+    ln_round_probability(bag, round).exp()
+}
+
+/// The chance of the bag producing every round of the game.
+pub(super) fn game_likelihood(bag: &CubeSet, game: &Game) -> f64 {
+    game.rounds
+        .iter()
+        .map(|round| round_probability(bag, round))
+        .product()
+}
+
+/// Find the bag of at most `max_total` cubes under which all the games are most likely, together
+/// with that likelihood. Only the colours that show up in the games are put in the bag, and `None`
+/// is returned when even the smallest possible bag holds more than `max_total` cubes.
+///
+/// Bigger bags don't always make the games less likely, so the search needs an upper bound.
+pub(super) fn maximum_likelihood_bag(games: &[Game], max_total: u32) -> Option<(CubeSet, f64)> {
+    let minimal = minimal_bag(games);
+    // the slack is at most `max_total`, so it fits in a u32 again
+    let slack = u64::from(max_total).checked_sub(minimal.total())? as u32;
+    let colours: Vec<&str> = minimal.colours().collect();
+
+    // try every way to spread the slack over the colours on top of the minimal bag
+    fn search(
+        colours: &[&str],
+        slack: u32,
+        bag: &mut CubeSet,
+        games: &[Game],
+        best: &mut (CubeSet, f64),
+    ) {
+        let Some((colour, rest)) = colours.split_first() else {
+            let ln_likelihood = ln_likelihood(bag, games);
+            if ln_likelihood > best.1 {
+                *best = (bag.clone(), ln_likelihood);
+            }
+            return;
+        };
+
+        let minimum = bag.get(colour);
+        for extra in 0..=slack {
+            bag.set(colour, minimum + extra);
+            search(rest, slack - extra, bag, games, best);
+        }
+        bag.set(colour, minimum);
+    }
+
+    let mut best = (minimal.clone(), ln_likelihood(&minimal, games));
+    search(&colours, slack, &mut minimal.clone(), games, &mut best);
+    let (bag, ln_likelihood) = best;
+    Some((bag, ln_likelihood.exp()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-12,
+            "{} is not close to {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_ln_choose() {
+        assert_close(ln_choose(39, 7), 15380937f64.ln());
+        assert_close(ln_choose(14, 0), 0.0);
+        assert_close(ln_choose(14, 14), 0.0);
+        // 2000000000 choose 2 without building anything for all the cubes
+        assert_close(ln_choose(2_000_000_000, 2), 1999999999e9f64.ln());
+    }
+
+    #[test]
+    fn test_round_probability() {
+        let bag = CubeSet::from([("red", 12), ("green", 13), ("blue", 14)]);
+        let round = CubeSet::from([("blue", 3), ("red", 4)]);
+
+        // (14 choose 3) * (12 choose 4) / (39 choose 7)
+        assert_close(round_probability(&bag, &round), 364.0 * 495.0 / 15380937.0);
+    }
+
+    #[test]
+    fn test_round_probability_impossible() {
+        let bag = CubeSet::from([("red", 12), ("green", 13), ("blue", 14)]);
+
+        assert_eq!(round_probability(&bag, &CubeSet::from([("red", 20)])), 0.0);
+        assert_eq!(
+            round_probability(&bag, &CubeSet::from([("yellow", 1)])),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_round_probability_whole_bag() {
+        let bag = CubeSet::from([("red", 2), ("blue", 3)]);
+
+        assert_close(round_probability(&bag, &bag), 1.0);
+    }
+
+    #[test]
+    fn test_game_likelihood() {
+        let (_, game) = Game::parse("Game 1: 2 red; 1 red, 1 blue").unwrap();
+        let bag = CubeSet::from([("red", 3), ("blue", 1)]);
+
+        // (3 choose 2) / (4 choose 2) * 3 * 1 / (4 choose 2)
+        assert_close(game_likelihood(&bag, &game), 0.5 * 0.5);
+    }
+
+    #[test]
+    fn test_maximum_likelihood_bag() {
+        let (_, game) = Game::parse("Game 1: 2 red; 1 red, 1 blue").unwrap();
+        let games = vec![game];
+
+        let (bag, likelihood) = maximum_likelihood_bag(&games, 6).unwrap();
+        assert_eq!(bag, CubeSet::from([("red", 3), ("blue", 1)]));
+        assert_close(likelihood, 0.25);
+
+        assert_eq!(maximum_likelihood_bag(&games, 2), None);
+    }
+}
@@ -78,48 +78,233 @@
 /// Adding up all of the gear ratios produces 467835.
 ///
 /// What is the sum of all of the gear ratios in your engine schematic?
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+
+mod assembly;
+mod edit;
+mod render;
 
 const INPUT: &str = include_str!("../input/day_03");
 
-pub fn run() {
-    let schematic = parse_schematic(INPUT);
+pub fn run(options: &[String]) {
+    // `--negative` reads a '-' in front of a number as its sign, `--tokens '->,<='` reads those
+    // multi-character symbols as a single symbol
+    let mode = ParseMode {
+        negative_numbers: options.iter().any(|option| option == "--negative"),
+        tokens: crate::option_value(options, "--tokens")
+            .map(|tokens| tokens.split(',').map(String::from).collect())
+            .unwrap_or_default(),
+    };
+    let schematic = parse_schematic(INPUT, &mode);
+
+    // numbers are adjacent to the 8 cells around them by default, `--neighbourhood` changes it to
+    // `4`, `8`, `manhattan:<radius>` or a stencil of offsets like `stencil:-1,0;1,0`
+    let neighbourhood = match crate::option_value(options, "--neighbourhood") {
+        Some(neighbourhood) => Neighbourhood::parse(neighbourhood).expect("invalid neighbourhood"),
+        None => Neighbourhood::EightConnected,
+    };
+
+    let parts = map_parts(&schematic, &neighbourhood);
+
+    // the gear rules default to the puzzle's, a '*' with exactly two numbers multiplied together;
+    // `--gear-symbols '*,#'`, `--gear-count 2` (or `2+` for at least two) and
+    // `--gear-combine product|sum|max` change them
+    let mut rule = GearRule::default();
+    if let Some(symbols) = crate::option_value(options, "--gear-symbols") {
+        rule.symbols = symbols.split(',').map(String::from).collect();
+    }
+    if let Some(count) = crate::option_value(options, "--gear-count") {
+        rule.count = match count.strip_suffix('+') {
+            Some(minimum) => GearCount::AtLeast(minimum.parse().expect("invalid gear count")),
+            None => GearCount::Exactly(count.parse().expect("invalid gear count")),
+        };
+    }
+    if let Some(combine) = crate::option_value(options, "--gear-combine") {
+        rule.combine = match combine {
+            "product" => Combine::Product,
+            "sum" => Combine::Sum,
+            "max" => Combine::Max,
+            other => panic!("unknown way to combine gear numbers: {}", other),
+        };
+    }
 
-    let parts = map_parts(&schematic);
+    // `--render ansi` or `--render html` draws the schematic with the parts highlighted
+    if let Some(format) = crate::option_value(options, "--render") {
+        let format = match format {
+            "ansi" => render::Format::Ansi,
+            "html" => render::Format::Html,
+            other => panic!("unknown render format: {}", other),
+        };
+        let size = render::size(INPUT);
+        println!(
+            "{}",
+            render::render(&schematic, &parts, &rule, size, format)
+        );
+    }
 
-    let sum_of_part_numbers: u32 = parts.iter().flat_map(Part::get_numbers).sum();
+    let sum_of_part_numbers: i64 = parts.iter().flat_map(Part::get_numbers).sum();
     println!(
         "The sum of all the part numbers in the engine schematic is: {}",
         sum_of_part_numbers
     );
 
-    let sum_of_gear_ratios: u32 = find_gear_ratios(&parts).iter().sum();
-    println!(
-        "The sum of all the gear ratios in the engine schematis is: {}",
-        sum_of_gear_ratios
+    match sum_of_gear_ratios(&parts, &rule) {
+        Some(sum_of_gear_ratios) => println!(
+            "The sum of all the gear ratios in the engine schematis is: {}",
+            sum_of_gear_ratios
+        ),
+        None => {
+            println!("The sum of all the gear ratios in the engine schematis is too big to count")
+        }
+    }
+
+    // `--assemblies` reports the groups of connected numbers and symbols
+    if options.iter().any(|option| option == "--assemblies") {
+        let assemblies = assembly::find_assemblies(&parts, &neighbourhood);
+        println!("The engine has {} assemblies", assemblies.len());
+        if let Some(largest) = assembly::largest_assembly(&assemblies) {
+            println!(
+                "The largest assembly has {} symbols and {} numbers, summing to {}",
+                largest.symbols.len(),
+                largest.numbers.len(),
+                largest.sum()
+            );
+        }
+        for assembly in assemblies.iter() {
+            let (x, y) = assembly.symbols[0];
+            println!(
+                "  the assembly at line {}, column {} sums to {}",
+                x + 1,
+                y + 1,
+                assembly.sum()
+            );
+        }
+    }
+
+    // `--shared` lists the numbers that are adjacent to more than one symbol
+    if options.iter().any(|option| option == "--shared") {
+        for (number, symbols) in find_shared_numbers(&parts) {
+            let (x, y) = number.start;
+            println!(
+                "{} at line {}, column {} is adjacent to {} symbols",
+                number.value,
+                x + 1,
+                y + 1,
+                symbols.len()
+            );
+        }
+    }
+
+    // `--set <line>,<column>,<character>` and `--clear <line>,<column>` edit a cell and report the
+    // sums again, without parsing and mapping the whole schematic again
+    let set = crate::option_value(options, "--set");
+    let clear = crate::option_value(options, "--clear");
+    if set.is_some() || clear.is_some() {
+        let mut editable = edit::EditableSchematic::new(INPUT, mode, neighbourhood, rule);
+        let mut edited = Ok(());
+        if let Some(edit) = set {
+            let (cell, ch) = edit.rsplit_once(',').expect("invalid edit");
+            let ch = ch.chars().next().expect("invalid edit");
+            edited = parse_cell(cell).and_then(|point| editable.set(point, ch).map_err(cell_error));
+        }
+        if let (Ok(()), Some(cell)) = (&edited, clear) {
+            edited = parse_cell(cell).and_then(|point| editable.clear(point).map_err(cell_error));
+        }
+        if let Err(error) = edited {
+            println!("Could not edit the schematic: {}", error);
+            return;
+        }
+        let sum_of_gear_ratios = match editable.sum_of_gear_ratios() {
+            Some(sum_of_gear_ratios) => sum_of_gear_ratios.to_string(),
+            None => "too big to count".to_string(),
+        };
+        println!(
+            "After editing, the sum of all the part numbers is {} and the sum of all the gear ratios is {}",
+            editable.sum_of_part_numbers(),
+            sum_of_gear_ratios
+        );
+    }
+}
+
+/// Read a cell given as `<line>,<column>`, counting from 1 like the rendered gears.
+fn parse_cell(cell: &str) -> Result<Point, String> {
+    let (line, column) = cell.split_once(',').expect("invalid cell");
+    let point = (
+        line.parse::<i32>().expect("invalid line") - 1,
+        column.parse::<i32>().expect("invalid column") - 1,
     );
+    match point {
+        (x, y) if x < 0 || y < 0 => Err(cell_error(point)),
+        _ => Ok(point),
+    }
+}
+
+fn cell_error((x, y): Point) -> String {
+    format!(
+        "line {}, column {} is before the start of the schematic, lines and columns count from 1",
+        x + 1,
+        y + 1
+    )
 }
 
 type Point = (i32, i32);
-type Schematic = HashMap<Point, String>;
+type Schematic = HashMap<Point, Cell>;
 
-#[derive(Debug)]
-struct Part {
-    #[allow(dead_code)]
-    position: Point,
-    identity: String,
-    numbers: Vec<u32>,
+/// What starts at a point in the schematic, empty cells aren't in the schematic at all.
+#[derive(Debug, Clone, PartialEq)]
+enum Cell {
+    /// A number, with the amount of characters it takes up and whether it was written with a '-'
+    /// in front, which "-0" needs to keep its sign.
+    Number {
+        value: i64,
+        length: usize,
+        signed: bool,
+    },
+    /// A symbol, which can take up more than one character.
+    Symbol(String),
 }
 
-impl PartialEq for Part {
-    fn eq(&self, other: &Self) -> bool {
-        self.position == other.position
-            && self.identity == other.identity
-            && self.numbers.len() == other.numbers.len()
-            && self.numbers.iter().all(|num| other.numbers.contains(num))
+impl Cell {
+    /// The characters the cell takes up in the schematic.
+    fn text(&self) -> String {
+        match self {
+            // pad with zeros, in case the number was written with leading zeros
+            &Cell::Number {
+                value,
+                length,
+                signed: true,
+            } => format!("-{:0width$}", value.unsigned_abs(), width = length - 1),
+            Cell::Number { value, length, .. } => format!("{:0width$}", value, width = length),
+            Cell::Symbol(identity) => identity.clone(),
+        }
     }
 }
 
+/// How the characters of the schematic are read.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct ParseMode {
+    /// Read a '-' directly in front of a number, but not after a digit, as its sign.
+    negative_numbers: bool,
+    /// Symbols of more than one character, the longest one that fits is used.
+    tokens: Vec<String>,
+}
+
+/// A number in the schematic, with the point of its first digit and how many digits it has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Number {
+    start: Point,
+    length: usize,
+    value: i64,
+}
+
+#[derive(Debug, PartialEq)]
+struct Part {
+    position: Point,
+    identity: String,
+    /// The adjacent numbers, ordered by their start.
+    numbers: Vec<Number>,
+}
+
 impl Part {
     fn new(position: Point, identity: String) -> Part {
         Part {
@@ -129,168 +314,395 @@ impl Part {
         }
     }
 
-    fn get_numbers(&self) -> &Vec<u32> {
-        &self.numbers
+    fn get_numbers(&self) -> Vec<i64> {
+        self.numbers.iter().map(|number| number.value).collect()
+    }
+}
+
+/// Which cells around a digit count as adjacent to it.
+#[derive(Debug, Clone, PartialEq)]
+enum Neighbourhood {
+    /// The cells above, below, left and right.
+    FourConnected,
+    /// The cells above, below, left and right, and diagonally.
+    EightConnected,
+    /// The cells within the given Manhattan distance.
+    Manhattan(i32),
+    /// The cells at the given offsets from the digit.
+    Stencil(Vec<Point>),
+}
+
+impl Neighbourhood {
+    /// Parse a neighbourhood like `4`, `8`, `manhattan:2` or `stencil:-1,0;1,0`.
+    fn parse(input: &str) -> Option<Neighbourhood> {
+        match input.split_once(':') {
+            None if input == "4" => Some(Neighbourhood::FourConnected),
+            None if input == "8" => Some(Neighbourhood::EightConnected),
+            Some(("manhattan", radius)) => Some(Neighbourhood::Manhattan(radius.parse().ok()?)),
+            Some(("stencil", offsets)) => {
+                let offsets = offsets
+                    .split(';')
+                    .map(|offset| {
+                        let (x, y) = offset.split_once(',')?;
+                        Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+                    })
+                    .collect::<Option<_>>()?;
+                Some(Neighbourhood::Stencil(offsets))
+            }
+            _ => None,
+        }
+    }
+
+    /// The offsets of the adjacent cells from a digit.
+    fn offsets(&self) -> Vec<Point> {
+        match self {
+            Neighbourhood::FourConnected => Neighbourhood::Manhattan(1).offsets(),
+            Neighbourhood::EightConnected => (-1..=1)
+                .flat_map(|x| (-1..=1).map(move |y| (x, y)))
+                .filter(|&offset| offset != (0, 0))
+                .collect(),
+            Neighbourhood::Manhattan(radius) => (-radius..=*radius)
+                .flat_map(|x| (-radius..=*radius).map(move |y| (x, y)))
+                .filter(|&(x, y)| x.abs() + y.abs() <= *radius && (x, y) != (0, 0))
+                .collect(),
+            Neighbourhood::Stencil(offsets) => offsets.clone(),
+        }
+    }
+
+    /// All the points adjacent to any of the digits of the number, each point only once.
+    fn around(&self, number: &Number) -> BTreeSet<Point> {
+        let (x_n, y_n) = number.start;
+        let offsets = self.offsets();
+        (0..number.length as i32)
+            .flat_map(|i| offsets.iter().map(move |(x, y)| (x_n + x, y_n + i + y)))
+            .collect()
     }
 }
 
-fn parse_schematic(input: &str) -> Schematic {
+fn parse_schematic(input: &str, mode: &ParseMode) -> Schematic {
+    let mut tokens: Vec<Vec<char>> = mode
+        .tokens
+        .iter()
+        .map(|token| token.chars().collect())
+        .filter(|token: &Vec<char>| !token.is_empty())
+        .collect();
+    // try the longest tokens first, so "->>" wins over "->"
+    tokens.sort_by_key(|token| std::cmp::Reverse(token.len()));
+
     let mut schematic = Schematic::new();
-    let mut current_number = None;
     for (x, line) in input.lines().enumerate() {
-        for (y, ch) in line.chars().enumerate() {
-            if ch.is_digit(10) {
+        let chars: Vec<char> = line.chars().collect();
+        let mut current_number: Option<(Point, String)> = None;
+        let mut y = 0;
+        while y < chars.len() {
+            let point = (x as i32, y as i32);
+            let ch = chars[y];
+            let token = tokens
+                .iter()
+                .find(|token| chars[y..].starts_with(token.as_slice()));
+
+            if token.is_none() && ch.is_ascii_digit() {
                 current_number = match current_number {
-                    None => Some(((x as i32, y as i32), ch.to_string())),
+                    None => Some((point, ch.to_string())),
                     Some((pos, mut s)) => {
                         s.push(ch);
                         Some((pos, s))
                     }
                 };
-            } else {
-                match current_number {
-                    Some((pos, s)) => {
-                        schematic.insert(pos, s);
-                        current_number = None;
-                    }
-                    None => {}
+                y += 1;
+                continue;
+            }
+
+            if let Some((pos, s)) = current_number.take() {
+                schematic.insert(pos, number_cell(&s));
+            }
+
+            let starts_negative_number = mode.negative_numbers
+                && ch == '-'
+                && chars.get(y + 1).is_some_and(char::is_ascii_digit)
+                && !(y > 0 && chars[y - 1].is_ascii_digit());
+            if token.is_none() && starts_negative_number {
+                current_number = Some((point, ch.to_string()));
+                y += 1;
+                continue;
+            }
+
+            match token {
+                Some(token) => {
+                    schematic.insert(point, Cell::Symbol(token.iter().collect()));
+                    y += token.len();
                 }
-                if ch != '.' {
-                    schematic.insert((x as i32, y as i32), ch.to_string());
+                None => {
+                    if ch != '.' {
+                        schematic.insert(point, Cell::Symbol(ch.to_string()));
+                    }
+                    y += 1;
                 }
             }
         }
-        match current_number {
-            Some((pos, s)) => {
-                schematic.insert(pos, s);
-                current_number = None;
-            }
-            None => {}
+        if let Some((pos, s)) = current_number {
+            schematic.insert(pos, number_cell(&s));
         }
     }
 
     schematic
 }
 
-fn map_parts(schematic: &Schematic) -> Vec<Part> {
+/// The cell for the characters of a number, these are only digits and maybe a leading '-'. A
+/// number too big to fit is kept as text, a symbol like any other.
+fn number_cell(s: &str) -> Cell {
+    match s.parse() {
+        Ok(value) => Cell::Number {
+            value,
+            length: s.chars().count(),
+            signed: s.starts_with('-'),
+        },
+        Err(_) => Cell::Symbol(s.to_string()),
+    }
+}
+
+fn map_parts(schematic: &Schematic, neighbourhood: &Neighbourhood) -> Vec<Part> {
     // split the schematic into numbers and parts
     let mut numbers = Vec::new();
     let mut parts = HashMap::new();
-    for (&point, s) in schematic.iter() {
-        match s.parse() {
-            Ok(number) => {
-                // if the string is parseable into a number, add it to the numbers
-                numbers.push((point, s, number));
+    // a symbol can take up more than one cell, keep track of which symbol is in every cell
+    let mut symbol_cells = HashMap::new();
+    for (&point, cell) in schematic.iter() {
+        match cell {
+            &Cell::Number { value, length, .. } => {
+                numbers.push(Number {
+                    start: point,
+                    length,
+                    value,
+                });
             }
-            Err(..) => {
-                // else add it to the parts
-                parts.insert(point, Part::new(point, s.clone()));
+            Cell::Symbol(identity) => {
+                let (x, y) = point;
+                for i in 0..identity.chars().count() as i32 {
+                    symbol_cells.insert((x, y + i), point);
+                }
+                parts.insert(point, Part::new(point, identity.clone()));
             }
         }
     }
 
     // map numbers to parts
-    for ((x_n, y_n), s, number) in numbers.into_iter() {
-        // iterate over all possible positions a part can
-        for x in (x_n - 1)..=(x_n + 1) {
-            for y in (y_n - 1)..=(y_n + s.len() as i32) {
-                if let Some(part) = parts.get_mut(&(x, y)) {
-                    // put the part number into the associated part's list
-                    part.numbers.push(number);
-                }
+    numbers.sort();
+    for number in numbers.into_iter() {
+        // find the symbols in all the possible positions, a symbol only counts once even when the
+        // number is next to more than one of its cells
+        let symbols: BTreeSet<Point> = neighbourhood
+            .around(&number)
+            .iter()
+            .filter_map(|neighbour| symbol_cells.get(neighbour).copied())
+            .collect();
+        for symbol in symbols {
+            // put the part number into the associated part's list
+            if let Some(part) = parts.get_mut(&symbol) {
+                part.numbers.push(number);
             }
         }
     }
 
-    return parts.into_values().collect();
+    parts.into_values().collect()
 }
 
-fn find_gear_ratios(parts: &Vec<Part>) -> Vec<u32> {
-    let gear_identity = "*".to_string();
-    parts
-        .iter()
-        .filter(|part| part.identity == gear_identity)
-        .filter(|part| part.numbers.len() == 2)
-        .map(|part| part.numbers.iter().product())
-        .collect()
+/// Index the numbers by the positions of all the symbols they are adjacent to.
+fn index_numbers(parts: &[Part]) -> HashMap<Number, Vec<Point>> {
+    let mut index: HashMap<Number, Vec<Point>> = HashMap::new();
+    for part in parts.iter() {
+        for &number in part.numbers.iter() {
+            index.entry(number).or_default().push(part.position);
+        }
+    }
+    index
+}
+
+/// Find the numbers adjacent to more than one symbol, ordered by their start, with those symbols.
+fn find_shared_numbers(parts: &[Part]) -> Vec<(Number, Vec<Point>)> {
+    let mut shared: Vec<(Number, Vec<Point>)> = index_numbers(parts)
+        .into_iter()
+        .filter(|(_, symbols)| symbols.len() > 1)
+        .map(|(number, mut symbols)| {
+            symbols.sort();
+            (number, symbols)
+        })
+        .collect();
+    shared.sort();
+    shared
+}
+
+/// How many numbers a part needs to be a gear.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GearCount {
+    Exactly(usize),
+    AtLeast(usize),
+}
+
+/// How the numbers of a gear are combined into its gear ratio.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Combine {
+    Product,
+    Sum,
+    Max,
+}
+
+/// What makes a part a gear, and how its gear ratio is calculated.
+#[derive(Debug, Clone, PartialEq)]
+struct GearRule {
+    /// The identities of the parts that can be gears.
+    symbols: Vec<String>,
+    count: GearCount,
+    combine: Combine,
+}
+
+impl Default for GearRule {
+    /// A gear is a '*' with exactly two part numbers, its ratio is those numbers multiplied.
+    fn default() -> GearRule {
+        GearRule {
+            symbols: vec!["*".to_string()],
+            count: GearCount::Exactly(2),
+            combine: Combine::Product,
+        }
+    }
+}
+
+impl GearRule {
+    fn is_gear(&self, part: &Part) -> bool {
+        let amount = part.numbers.len();
+        self.symbols.contains(&part.identity)
+            && match self.count {
+                GearCount::Exactly(count) => amount == count,
+                GearCount::AtLeast(count) => amount >= count,
+            }
+    }
+
+    /// The gear ratio of the part, if it is a gear. The ratio is `None` when it doesn't fit in an
+    /// `i64`.
+    fn ratio(&self, part: &Part) -> Option<Option<i64>> {
+        if !self.is_gear(part) {
+            return None;
+        }
+        let mut values = part.numbers.iter().map(|number| number.value);
+        Some(match self.combine {
+            Combine::Product => values.try_fold(1i64, |product, value| product.checked_mul(value)),
+            Combine::Sum => values.try_fold(0i64, |sum, value| sum.checked_add(value)),
+            Combine::Max => Some(values.max().unwrap_or(0)),
+        })
+    }
+}
+
+impl Combine {
+    /// Write out how the values are combined, like `467 * 35`.
+    fn show(&self, values: &[i64]) -> String {
+        let values: Vec<String> = values.iter().map(i64::to_string).collect();
+        match self {
+            Combine::Product => values.join(" * "),
+            Combine::Sum => values.join(" + "),
+            Combine::Max => format!("max({})", values.join(", ")),
+        }
+    }
+}
+
+/// The gear ratios of all the gears, `None` when one of them doesn't fit in an `i64`.
+fn find_gear_ratios(parts: &[Part], rule: &GearRule) -> Option<Vec<i64>> {
+    parts.iter().filter_map(|part| rule.ratio(part)).collect()
+}
+
+/// The sum of all the gear ratios, `None` when it doesn't fit in an `i64`.
+fn sum_of_gear_ratios(parts: &[Part], rule: &GearRule) -> Option<i64> {
+    find_gear_ratios(parts, rule)?
+        .into_iter()
+        .try_fold(0, i64::checked_add)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    pub(super) const EXAMPLE: &str = "\
+        467..114..\n\
+        ...*......\n\
+        ..35..633.\n\
+        ......#...\n\
+        617*......\n\
+        .....+.58.\n\
+        ..592.....\n\
+        ......755.\n\
+        ...$.*....\n\
+        .664.598..";
+
     fn example_schematic() -> Schematic {
         let mut expected_schematic = Schematic::new();
-        expected_schematic.insert((0, 0), String::from("467"));
-        expected_schematic.insert((0, 5), String::from("114"));
-        expected_schematic.insert((1, 3), String::from("*"));
-        expected_schematic.insert((2, 2), String::from("35"));
-        expected_schematic.insert((2, 6), String::from("633"));
-        expected_schematic.insert((3, 6), String::from("#"));
-        expected_schematic.insert((4, 0), String::from("617"));
-        expected_schematic.insert((4, 3), String::from("*"));
-        expected_schematic.insert((5, 5), String::from("+"));
-        expected_schematic.insert((5, 7), String::from("58"));
-        expected_schematic.insert((6, 2), String::from("592"));
-        expected_schematic.insert((7, 6), String::from("755"));
-        expected_schematic.insert((8, 3), String::from("$"));
-        expected_schematic.insert((8, 5), String::from("*"));
-        expected_schematic.insert((9, 1), String::from("664"));
-        expected_schematic.insert((9, 5), String::from("598"));
+        expected_schematic.insert((0, 0), number_cell("467"));
+        expected_schematic.insert((0, 5), number_cell("114"));
+        expected_schematic.insert((1, 3), Cell::Symbol("*".to_string()));
+        expected_schematic.insert((2, 2), number_cell("35"));
+        expected_schematic.insert((2, 6), number_cell("633"));
+        expected_schematic.insert((3, 6), Cell::Symbol("#".to_string()));
+        expected_schematic.insert((4, 0), number_cell("617"));
+        expected_schematic.insert((4, 3), Cell::Symbol("*".to_string()));
+        expected_schematic.insert((5, 5), Cell::Symbol("+".to_string()));
+        expected_schematic.insert((5, 7), number_cell("58"));
+        expected_schematic.insert((6, 2), number_cell("592"));
+        expected_schematic.insert((7, 6), number_cell("755"));
+        expected_schematic.insert((8, 3), Cell::Symbol("$".to_string()));
+        expected_schematic.insert((8, 5), Cell::Symbol("*".to_string()));
+        expected_schematic.insert((9, 1), number_cell("664"));
+        expected_schematic.insert((9, 5), number_cell("598"));
 
         expected_schematic
     }
 
+    fn number(start: Point, value: i64) -> Number {
+        Number {
+            start,
+            length: value.to_string().len(),
+            value,
+        }
+    }
+
     fn example_parts() -> Vec<Part> {
         vec![
             Part {
                 position: (1, 3),
                 identity: "*".to_string(),
-                numbers: vec![35, 467],
+                numbers: vec![number((0, 0), 467), number((2, 2), 35)],
             },
             Part {
                 position: (3, 6),
                 identity: "#".to_string(),
-                numbers: vec![633],
+                numbers: vec![number((2, 6), 633)],
             },
             Part {
                 position: (4, 3),
                 identity: "*".to_string(),
-                numbers: vec![617],
+                numbers: vec![number((4, 0), 617)],
             },
             Part {
                 position: (5, 5),
                 identity: "+".to_string(),
-                numbers: vec![592],
+                numbers: vec![number((6, 2), 592)],
             },
             Part {
                 position: (8, 3),
                 identity: "$".to_string(),
-                numbers: vec![664],
+                numbers: vec![number((9, 1), 664)],
             },
             Part {
                 position: (8, 5),
                 identity: "*".to_string(),
-                numbers: vec![598, 755],
+                numbers: vec![number((7, 6), 755), number((9, 5), 598)],
             },
         ]
     }
 
     #[test]
     fn test_parse_schematic_1() {
-        let input = "\
-           467..114..\n\
-           ...*......\n\
-           ..35..633.\n\
-           ......#...\n\
-           617*......\n\
-           .....+.58.\n\
-           ..592.....\n\
-           ......755.\n\
-           ...$.*....\n\
-           .664.598..";
-
-        assert_eq!(parse_schematic(input), example_schematic());
+        assert_eq!(
+            parse_schematic(EXAMPLE, &ParseMode::default()),
+            example_schematic()
+        );
     }
 
     #[test]
@@ -298,14 +710,17 @@ mod tests {
         let input = "1";
 
         let mut expected_schematic = Schematic::new();
-        expected_schematic.insert((0, 0), String::from("1"));
+        expected_schematic.insert((0, 0), number_cell("1"));
 
-        assert_eq!(parse_schematic(input), expected_schematic);
+        assert_eq!(
+            parse_schematic(input, &ParseMode::default()),
+            expected_schematic
+        );
     }
 
     #[test]
     fn test_map_parts() {
-        let actual_parts = map_parts(&example_schematic());
+        let actual_parts = map_parts(&example_schematic(), &Neighbourhood::EightConnected);
         let expected_parts = example_parts();
 
         // check whether the lengths of the parts list are equal
@@ -320,9 +735,285 @@ mod tests {
     fn test_gear_ratios() {
         let expected_gear_ratios = vec![16345, 451490];
 
-        let mut actual_gear_ratios = find_gear_ratios(&example_parts());
+        let mut actual_gear_ratios =
+            find_gear_ratios(&example_parts(), &GearRule::default()).unwrap();
         actual_gear_ratios.sort();
 
         assert_eq!(actual_gear_ratios, expected_gear_ratios);
     }
+
+    #[test]
+    fn test_map_parts_identical_numbers() {
+        // two 12s around a single symbol used to be indistinguishable
+        let schematic = parse_schematic("12.12\n..*..", &ParseMode::default());
+        let parts = map_parts(&schematic, &Neighbourhood::EightConnected);
+
+        assert_eq!(
+            parts,
+            vec![Part {
+                position: (1, 2),
+                identity: "*".to_string(),
+                numbers: vec![number((0, 0), 12), number((0, 3), 12)],
+            }]
+        );
+        assert_eq!(
+            find_gear_ratios(&parts, &GearRule::default()),
+            Some(vec![144])
+        );
+    }
+
+    #[test]
+    fn test_find_shared_numbers() {
+        let schematic = parse_schematic("#...\n.12*\n....\n.5..\n..$.", &ParseMode::default());
+        let parts = map_parts(&schematic, &Neighbourhood::EightConnected);
+
+        assert_eq!(
+            find_shared_numbers(&parts),
+            vec![(number((1, 1), 12), vec![(0, 0), (1, 3)])]
+        );
+        assert_eq!(index_numbers(&parts)[&number((3, 1), 5)], vec![(4, 2)]);
+        assert!(find_shared_numbers(&map_parts(
+            &example_schematic(),
+            &Neighbourhood::EightConnected
+        ))
+        .is_empty());
+    }
+
+    #[test]
+    fn test_gear_rule_symbols() {
+        let rule = GearRule {
+            symbols: vec!["*".to_string(), "$".to_string(), "+".to_string()],
+            count: GearCount::Exactly(1),
+            ..GearRule::default()
+        };
+
+        let mut actual_gear_ratios = find_gear_ratios(&example_parts(), &rule).unwrap();
+        actual_gear_ratios.sort();
+
+        assert_eq!(actual_gear_ratios, vec![592, 617, 664]);
+    }
+
+    #[test]
+    fn test_gear_rule_at_least() {
+        let rule = GearRule {
+            symbols: vec!["*".to_string(), "#".to_string()],
+            count: GearCount::AtLeast(1),
+            ..GearRule::default()
+        };
+
+        let mut actual_gear_ratios = find_gear_ratios(&example_parts(), &rule).unwrap();
+        actual_gear_ratios.sort();
+
+        assert_eq!(actual_gear_ratios, vec![617, 633, 16345, 451490]);
+    }
+
+    #[test]
+    fn test_gear_rule_combine() {
+        let sum = GearRule {
+            combine: Combine::Sum,
+            ..GearRule::default()
+        };
+        let mut actual_gear_ratios = find_gear_ratios(&example_parts(), &sum).unwrap();
+        actual_gear_ratios.sort();
+        assert_eq!(actual_gear_ratios, vec![502, 1353]);
+
+        let max = GearRule {
+            combine: Combine::Max,
+            ..GearRule::default()
+        };
+        let mut actual_gear_ratios = find_gear_ratios(&example_parts(), &max).unwrap();
+        actual_gear_ratios.sort();
+        assert_eq!(actual_gear_ratios, vec![467, 755]);
+
+        assert_eq!(Combine::Max.show(&[467, 35]), "max(467, 35)");
+    }
+
+    #[test]
+    fn test_gear_ratios_too_big() {
+        let rule = GearRule {
+            count: GearCount::AtLeast(2),
+            ..GearRule::default()
+        };
+
+        // 1000000000 three times over doesn't fit in an i64
+        let input = "1000000000*1000000000\n..........1000000000";
+        let parts = map_parts(
+            &parse_schematic(input, &ParseMode::default()),
+            &Neighbourhood::EightConnected,
+        );
+        assert_eq!(rule.ratio(&parts[0]), Some(None));
+        assert_eq!(find_gear_ratios(&parts, &rule), None);
+        assert_eq!(sum_of_gear_ratios(&parts, &rule), None);
+
+        // two gears that fit on their own, but not added up
+        let input = "3000000000*2000000000\n.....................\n3000000000*2000000000";
+        let parts = map_parts(
+            &parse_schematic(input, &ParseMode::default()),
+            &Neighbourhood::EightConnected,
+        );
+        assert_eq!(
+            find_gear_ratios(&parts, &rule),
+            Some(vec![6000000000000000000, 6000000000000000000])
+        );
+        assert_eq!(sum_of_gear_ratios(&parts, &rule), None);
+    }
+
+    fn sum_of_part_numbers(neighbourhood: &Neighbourhood) -> i64 {
+        map_parts(&example_schematic(), neighbourhood)
+            .iter()
+            .flat_map(Part::get_numbers)
+            .sum()
+    }
+
+    #[test]
+    fn test_neighbourhoods() {
+        assert_eq!(sum_of_part_numbers(&Neighbourhood::EightConnected), 4361);
+        // 467, 617 and 664 only touch their symbol diagonally
+        assert_eq!(sum_of_part_numbers(&Neighbourhood::FourConnected), 2547);
+        assert_eq!(sum_of_part_numbers(&Neighbourhood::Manhattan(1)), 2547);
+        // further away 114, 58 and 35 twice get counted as well
+        assert_eq!(sum_of_part_numbers(&Neighbourhood::Manhattan(2)), 5638);
+        assert_eq!(sum_of_part_numbers(&Neighbourhood::Manhattan(3)), 8533);
+        // only directly above or below a digit
+        let vertical = Neighbourhood::Stencil(vec![(-1, 0), (1, 0)]);
+        assert_eq!(sum_of_part_numbers(&vertical), 1930);
+    }
+
+    #[test]
+    fn test_neighbourhood_parse() {
+        assert_eq!(
+            Neighbourhood::parse("4"),
+            Some(Neighbourhood::FourConnected)
+        );
+        assert_eq!(
+            Neighbourhood::parse("8"),
+            Some(Neighbourhood::EightConnected)
+        );
+        assert_eq!(
+            Neighbourhood::parse("manhattan:2"),
+            Some(Neighbourhood::Manhattan(2))
+        );
+        assert_eq!(
+            Neighbourhood::parse("stencil:-1,0;1, 0"),
+            Some(Neighbourhood::Stencil(vec![(-1, 0), (1, 0)]))
+        );
+        assert_eq!(Neighbourhood::parse("stencil:-1"), None);
+        assert_eq!(Neighbourhood::parse("6"), None);
+    }
+
+    #[test]
+    fn test_neighbourhood_around() {
+        let number = number((3, 4), 12);
+
+        assert_eq!(
+            Neighbourhood::FourConnected.around(&number),
+            BTreeSet::from([
+                (2, 4),
+                (2, 5),
+                (3, 3),
+                (3, 4),
+                (3, 5),
+                (3, 6),
+                (4, 4),
+                (4, 5)
+            ])
+        );
+        assert_eq!(Neighbourhood::EightConnected.around(&number).len(), 12);
+    }
+
+    #[test]
+    fn test_parse_schematic_negative_numbers() {
+        let mode = ParseMode {
+            negative_numbers: true,
+            ..ParseMode::default()
+        };
+
+        let mut expected_schematic = Schematic::new();
+        expected_schematic.insert((0, 0), number_cell("-12"));
+        expected_schematic.insert((0, 4), number_cell("3"));
+        expected_schematic.insert((0, 5), Cell::Symbol("-".to_string()));
+        expected_schematic.insert((0, 6), number_cell("4"));
+        expected_schematic.insert((1, 1), Cell::Symbol("*".to_string()));
+        expected_schematic.insert((1, 3), Cell::Symbol("-".to_string()));
+
+        // a '-' after a digit or without digits after it is still a symbol
+        assert_eq!(parse_schematic("-12.3-4\n.*.-", &mode), expected_schematic);
+
+        let parts = map_parts(
+            &parse_schematic("-12.3-4\n.*.-", &mode),
+            &Neighbourhood::EightConnected,
+        );
+        let numbers: i64 = parts.iter().flat_map(Part::get_numbers).sum();
+        // -12 is adjacent to the '*' and the second '-', 3 to both '-' symbols and 4 to the first
+        assert_eq!(numbers, -12 - 12 + 3 + 3 + 4);
+    }
+
+    #[test]
+    fn test_parse_schematic_number_too_big() {
+        let input = format!("12345678901234567890123\n{}7", ".".repeat(23));
+        let schematic = parse_schematic(&input, &ParseMode::default());
+
+        // the number that doesn't fit is kept as text, next to the 7 like a symbol
+        assert_eq!(
+            schematic.get(&(0, 0)),
+            Some(&Cell::Symbol("12345678901234567890123".to_string()))
+        );
+        let parts = map_parts(&schematic, &Neighbourhood::EightConnected);
+        let numbers: i64 = parts.iter().flat_map(Part::get_numbers).sum();
+        assert_eq!(numbers, 7);
+    }
+
+    #[test]
+    fn test_parse_schematic_tokens() {
+        let mode = ParseMode {
+            negative_numbers: true,
+            tokens: vec!["->".to_string(), "->>".to_string()],
+        };
+
+        let mut expected_schematic = Schematic::new();
+        expected_schematic.insert((0, 0), number_cell("1"));
+        expected_schematic.insert((0, 1), Cell::Symbol("->".to_string()));
+        expected_schematic.insert((0, 3), number_cell("2"));
+        expected_schematic.insert((0, 5), Cell::Symbol("->>".to_string()));
+        expected_schematic.insert((0, 8), number_cell("-5"));
+
+        assert_eq!(parse_schematic("1->2.->>-5", &mode), expected_schematic);
+    }
+
+    #[test]
+    fn test_map_parts_multi_character_symbol() {
+        let mode = ParseMode {
+            tokens: vec!["<=>".to_string()],
+            ..ParseMode::default()
+        };
+        let schematic = parse_schematic("7....\n.<=>.\n...10", &mode);
+
+        assert_eq!(
+            map_parts(&schematic, &Neighbourhood::EightConnected),
+            vec![Part {
+                position: (1, 1),
+                identity: "<=>".to_string(),
+                numbers: vec![number((0, 0), 7), number((2, 3), 10)],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_cell_text() {
+        assert_eq!(number_cell("467").text(), "467");
+        assert_eq!(number_cell("007").text(), "007");
+        assert_eq!(number_cell("-07").text(), "-07");
+        assert_eq!(number_cell("-0").text(), "-0");
+        assert_eq!(number_cell("-000").text(), "-000");
+        assert_eq!(number_cell("00").text(), "00");
+        assert_eq!(Cell::Symbol("<=>".to_string()).text(), "<=>");
+    }
+
+    #[test]
+    fn test_parse_cell() {
+        assert_eq!(parse_cell("1,1"), Ok((0, 0)));
+        assert_eq!(parse_cell("12,3"), Ok((11, 2)));
+        assert!(parse_cell("0,1").is_err());
+        assert!(parse_cell("4,-2").is_err());
+    }
 }
@@ -0,0 +1,211 @@
+/// Group the numbers and symbols of the engine into assemblies: everything that is connected
+/// through adjacency, directly or through other numbers and symbols, belongs to the same assembly.
+use super::{Neighbourhood, Number, Part, Point};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+/// A node in the graph of the engine, numbers and symbols are linked when they're adjacent, whether
+/// to a number or a symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Node {
+    Symbol(Point),
+    Number(Number),
+}
+
+/// A group of connected symbols and numbers.
+#[derive(Debug, PartialEq)]
+pub(super) struct Assembly {
+    /// The positions of the symbols, in order.
+    pub(super) symbols: Vec<Point>,
+    /// The numbers, ordered by their start.
+    pub(super) numbers: Vec<Number>,
+}
+
+impl Assembly {
+    pub(super) fn size(&self) -> usize {
+        self.symbols.len() + self.numbers.len()
+    }
+
+    /// The sum of all the numbers in the assembly, each number only counts once.
+    pub(super) fn sum(&self) -> i64 {
+        self.numbers.iter().map(|number| number.value).sum()
+    }
+}
+
+/// Find all the assemblies in the parts, ordered by their first symbol. Numbers that aren't next to
+/// any symbol aren't part numbers, so they're not in any assembly.
+pub(super) fn find_assemblies(parts: &[Part], neighbourhood: &Neighbourhood) -> Vec<Assembly> {
+    // link every symbol to its numbers and back
+    let mut links: BTreeMap<Node, BTreeSet<Node>> = BTreeMap::new();
+    for part in parts.iter() {
+        let symbol = Node::Symbol(part.position);
+        links.entry(symbol).or_default();
+        for &number in part.numbers.iter() {
+            links
+                .entry(symbol)
+                .or_default()
+                .insert(Node::Number(number));
+            links
+                .entry(Node::Number(number))
+                .or_default()
+                .insert(symbol);
+        }
+    }
+
+    // link the symbols next to symbols and the numbers next to numbers, the same way a number is
+    // next to a symbol: a cell of the one plus an offset lands on a cell of the other
+    let mut cells: HashMap<Point, Node> = HashMap::new();
+    for part in parts.iter() {
+        let (x, y) = part.position;
+        for i in 0..part.identity.chars().count() as i32 {
+            cells.insert((x, y + i), Node::Symbol(part.position));
+        }
+        for number in part.numbers.iter() {
+            let (x, y) = number.start;
+            for i in 0..number.length as i32 {
+                cells.insert((x, y + i), Node::Number(*number));
+            }
+        }
+    }
+    let offsets = neighbourhood.offsets();
+    for (&(x, y), &node) in cells.iter() {
+        for (o_x, o_y) in offsets.iter() {
+            let Some(&other) = cells.get(&(x + o_x, y + o_y)) else {
+                continue;
+            };
+            let same_kind = matches!(
+                (node, other),
+                (Node::Symbol(_), Node::Symbol(_)) | (Node::Number(_), Node::Number(_))
+            );
+            if same_kind && other != node {
+                links.entry(node).or_default().insert(other);
+                links.entry(other).or_default().insert(node);
+            }
+        }
+    }
+
+    // walk through the links from every node not yet in an assembly, the symbols come first in the
+    // map so every assembly is found from its first symbol
+    let mut visited = BTreeSet::new();
+    let mut assemblies = Vec::new();
+    for &start in links.keys() {
+        if !visited.insert(start) {
+            continue;
+        }
+        let mut assembly = Assembly {
+            symbols: Vec::new(),
+            numbers: Vec::new(),
+        };
+        let mut queue = VecDeque::from([start]);
+        while let Some(node) = queue.pop_front() {
+            match node {
+                Node::Symbol(position) => assembly.symbols.push(position),
+                Node::Number(number) => assembly.numbers.push(number),
+            }
+            for &next in links[&node].iter() {
+                if visited.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        assembly.symbols.sort();
+        assembly.numbers.sort();
+        assemblies.push(assembly);
+    }
+
+    assemblies
+}
+
+/// The assembly with the most symbols and numbers, the first one when there's a tie.
+pub(super) fn largest_assembly(assemblies: &[Assembly]) -> Option<&Assembly> {
+    assemblies
+        .iter()
+        .rev()
+        .max_by_key(|assembly| assembly.size())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::EXAMPLE;
+    use super::super::{map_parts, parse_schematic, Neighbourhood, ParseMode};
+    use super::*;
+
+    fn assemblies(input: &str) -> Vec<Assembly> {
+        let schematic = parse_schematic(input, &ParseMode::default());
+        let neighbourhood = Neighbourhood::EightConnected;
+        find_assemblies(&map_parts(&schematic, &neighbourhood), &neighbourhood)
+    }
+
+    #[test]
+    fn test_find_assemblies_example() {
+        let assemblies = assemblies(EXAMPLE);
+
+        // every symbol is on its own in the example
+        assert_eq!(assemblies.len(), 6);
+        let sums: Vec<i64> = assemblies.iter().map(Assembly::sum).collect();
+        assert_eq!(sums, vec![502, 633, 617, 592, 664, 1353]);
+        assert_eq!(
+            largest_assembly(&assemblies).map(|assembly| assembly.symbols.clone()),
+            Some(vec![(1, 3)])
+        );
+    }
+
+    #[test]
+    fn test_find_assemblies_chained() {
+        // 12 links the '*' and the '#', which links 7 as well; the '$' and 5 are separate, and the
+        // '%' has no numbers at all
+        let assemblies = assemblies(
+            "\
+            *12#\n\
+            ...7\n\
+            ....\n\
+            $5.%",
+        );
+
+        assert_eq!(assemblies.len(), 3);
+        assert_eq!(
+            assemblies[0],
+            Assembly {
+                symbols: vec![(0, 0), (0, 3)],
+                numbers: vec![
+                    Number {
+                        start: (0, 1),
+                        length: 2,
+                        value: 12
+                    },
+                    Number {
+                        start: (1, 3),
+                        length: 1,
+                        value: 7
+                    },
+                ],
+            }
+        );
+        assert_eq!(assemblies[0].sum(), 19);
+        assert_eq!(assemblies[1].symbols, vec![(3, 0)]);
+        assert_eq!(assemblies[1].sum(), 5);
+        assert_eq!(assemblies[2].size(), 1);
+        assert_eq!(assemblies[2].sum(), 0);
+        assert_eq!(largest_assembly(&assemblies), Some(&assemblies[0]));
+    }
+
+    #[test]
+    fn test_find_assemblies_adjacent_numbers_and_symbols() {
+        // 12 and 3 are next to each other, linking the '$' and the '%'; the '*' and the '#' are
+        // next to each other without any numbers
+        let assemblies = assemblies(
+            "\
+            $....\n\
+            .12..\n\
+            ...3.\n\
+            ....%\n\
+            .....\n\
+            *#...",
+        );
+
+        assert_eq!(assemblies.len(), 2);
+        assert_eq!(assemblies[0].symbols, vec![(0, 0), (3, 4)]);
+        assert_eq!(assemblies[0].sum(), 15);
+        assert_eq!(assemblies[1].symbols, vec![(5, 0), (5, 1)]);
+        assert_eq!(assemblies[1].sum(), 0);
+    }
+}
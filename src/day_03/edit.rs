@@ -0,0 +1,390 @@
+/// A schematic that can be edited cell by cell, for what-if analysis. Only the line that changed is
+/// parsed again, and only the parts around the numbers and symbols that changed are mapped again,
+/// keeping the sums of the part numbers and the gear ratios up to date along the way.
+use super::{
+    map_parts, parse_schematic, Cell, GearRule, Neighbourhood, Number, ParseMode, Part, Point,
+    Schematic,
+};
+use std::collections::{BTreeSet, HashMap};
+
+pub(super) struct EditableSchematic {
+    lines: Vec<Vec<char>>,
+    mode: ParseMode,
+    neighbourhood: Neighbourhood,
+    rule: GearRule,
+    schematic: Schematic,
+    parts: HashMap<Point, Part>,
+    /// The start of the number for every cell with a digit in it.
+    digit_cells: HashMap<Point, Point>,
+    /// The position of the symbol for every cell that is part of a symbol.
+    symbol_cells: HashMap<Point, Point>,
+    sum_of_part_numbers: i64,
+    /// The sum of the gear ratios that fit in an `i64`, it can go past `i64::MAX` while editing.
+    sum_of_gear_ratios: i128,
+    /// The amount of gears with a ratio too big to count.
+    too_big_gears: i64,
+}
+
+impl EditableSchematic {
+    pub(super) fn new(
+        input: &str,
+        mode: ParseMode,
+        neighbourhood: Neighbourhood,
+        rule: GearRule,
+    ) -> EditableSchematic {
+        let schematic = parse_schematic(input, &mode);
+        let parts = map_parts(&schematic, &neighbourhood);
+
+        let mut editable = EditableSchematic {
+            lines: input.lines().map(|line| line.chars().collect()).collect(),
+            mode,
+            neighbourhood,
+            rule,
+            schematic: Schematic::new(),
+            parts: HashMap::new(),
+            digit_cells: HashMap::new(),
+            symbol_cells: HashMap::new(),
+            sum_of_part_numbers: 0,
+            sum_of_gear_ratios: 0,
+            too_big_gears: 0,
+        };
+        for (point, cell) in schematic.into_iter() {
+            editable.insert_cell(point, cell);
+        }
+        // the symbols are in already without any numbers, swap them for the mapped parts
+        for part in parts.into_iter() {
+            editable.add_contribution(&part, 1);
+            if let Some(empty) = editable.parts.insert(part.position, part) {
+                editable.add_contribution(&empty, -1);
+            }
+        }
+        editable
+    }
+
+    pub(super) fn sum_of_part_numbers(&self) -> i64 {
+        self.sum_of_part_numbers
+    }
+
+    /// The sum of the gear ratios, `None` when it doesn't fit in an `i64`.
+    pub(super) fn sum_of_gear_ratios(&self) -> Option<i64> {
+        match self.too_big_gears {
+            0 => i64::try_from(self.sum_of_gear_ratios).ok(),
+            _ => None,
+        }
+    }
+
+    /// Empty the cell, like putting a '.' in it.
+    pub(super) fn clear(&mut self, point: Point) -> Result<(), Point> {
+        self.set(point, '.')
+    }
+
+    /// Put a character in a cell, the schematic grows when the cell is after its last line or
+    /// column. Fails with the point when it's before the first line or column.
+    pub(super) fn set(&mut self, (x, y): Point, ch: char) -> Result<(), Point> {
+        let (Ok(line), Ok(column)) = (usize::try_from(x), usize::try_from(y)) else {
+            return Err((x, y));
+        };
+        if self.lines.len() <= line {
+            self.lines.resize(line + 1, Vec::new());
+        }
+        if self.lines[line].len() <= column {
+            self.lines[line].resize(column + 1, '.');
+        }
+        if self.lines[line][column] == ch {
+            return Ok(());
+        }
+        self.lines[line][column] = ch;
+
+        // only the changed line has to be parsed again, nothing spans more than one line
+        let text: String = self.lines[line].iter().collect();
+        let new_cells: HashMap<Point, Cell> = parse_schematic(&text, &self.mode)
+            .into_iter()
+            .map(|((_, y), cell)| ((x, y), cell))
+            .collect();
+        let old_cells: HashMap<Point, Cell> = self
+            .schematic
+            .iter()
+            .filter(|((x_c, _), _)| *x_c == x)
+            .map(|(&point, cell)| (point, cell.clone()))
+            .collect();
+
+        // the parts next to a number that changed have to be mapped again
+        let mut affected = BTreeSet::new();
+        for (&point, cell) in old_cells.iter() {
+            if new_cells.get(&point) == Some(cell) {
+                continue;
+            }
+            affected.extend(self.symbols_around(point, cell));
+            self.remove_cell(point);
+        }
+        for (&point, cell) in new_cells.iter() {
+            if old_cells.get(&point) == Some(cell) {
+                continue;
+            }
+            self.insert_cell(point, cell.clone());
+            affected.extend(self.symbols_around(point, cell));
+        }
+
+        for position in affected {
+            self.remap_part(position);
+        }
+        Ok(())
+    }
+
+    /// The positions of the symbols adjacent to a number cell, or of the symbol itself.
+    fn symbols_around(&self, point: Point, cell: &Cell) -> BTreeSet<Point> {
+        match cell {
+            &Cell::Number { value, length, .. } => {
+                let number = Number {
+                    start: point,
+                    length,
+                    value,
+                };
+                self.neighbourhood
+                    .around(&number)
+                    .iter()
+                    .filter_map(|neighbour| self.symbol_cells.get(neighbour).copied())
+                    .collect()
+            }
+            Cell::Symbol(_) => BTreeSet::from([point]),
+        }
+    }
+
+    fn insert_cell(&mut self, (x, y): Point, cell: Cell) {
+        let owners = match &cell {
+            Cell::Number { .. } => &mut self.digit_cells,
+            Cell::Symbol(_) => &mut self.symbol_cells,
+        };
+        let length = cell.text().chars().count() as i32;
+        for i in 0..length {
+            owners.insert((x, y + i), (x, y));
+        }
+        if let Cell::Symbol(identity) = &cell {
+            let part = Part::new((x, y), identity.clone());
+            self.add_contribution(&part, 1);
+            self.parts.insert((x, y), part);
+        }
+        self.schematic.insert((x, y), cell);
+    }
+
+    fn remove_cell(&mut self, (x, y): Point) {
+        let Some(cell) = self.schematic.remove(&(x, y)) else {
+            return;
+        };
+        let owners = match &cell {
+            Cell::Number { .. } => &mut self.digit_cells,
+            Cell::Symbol(_) => &mut self.symbol_cells,
+        };
+        for i in 0..cell.text().chars().count() as i32 {
+            owners.remove(&(x, y + i));
+        }
+        if let Some(part) = self.parts.remove(&(x, y)) {
+            self.add_contribution(&part, -1);
+        }
+    }
+
+    /// Find the numbers adjacent to the symbol again, the same way `map_parts` does.
+    fn remap_part(&mut self, position: Point) {
+        let Some(part) = self.parts.remove(&position) else {
+            // the symbol has been removed
+            return;
+        };
+        self.add_contribution(&part, -1);
+
+        // a number is adjacent when one of its digits plus an offset lands on the symbol
+        let (x, y) = position;
+        let offsets = self.neighbourhood.offsets();
+        let numbers: BTreeSet<Number> = (0..part.identity.chars().count() as i32)
+            .flat_map(|i| offsets.iter().map(move |(o_x, o_y)| (x - o_x, y + i - o_y)))
+            .filter_map(|digit| self.digit_cells.get(&digit))
+            .filter_map(|&start| match self.schematic.get(&start) {
+                Some(&Cell::Number { value, length, .. }) => Some(Number {
+                    start,
+                    length,
+                    value,
+                }),
+                _ => None,
+            })
+            .collect();
+
+        let part = Part {
+            numbers: numbers.into_iter().collect(),
+            ..part
+        };
+        self.add_contribution(&part, 1);
+        self.parts.insert(position, part);
+    }
+
+    /// Add (or with a sign of -1, remove) what the part adds to the sums.
+    fn add_contribution(&mut self, part: &Part, sign: i64) {
+        self.sum_of_part_numbers += sign * part.get_numbers().iter().sum::<i64>();
+        match self.rule.ratio(part) {
+            Some(Some(ratio)) => self.sum_of_gear_ratios += sign as i128 * ratio as i128,
+            Some(None) => self.too_big_gears += sign,
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::sum_of_gear_ratios;
+    use super::super::tests::EXAMPLE;
+    use super::*;
+    use crate::random::XorShift;
+
+    /// Parse and map the whole schematic again, to compare the edited sums with.
+    fn full_sums(editable: &EditableSchematic) -> (i64, Option<i64>) {
+        let lines: Vec<String> = editable
+            .lines
+            .iter()
+            .map(|line| line.iter().collect())
+            .collect();
+        let schematic = parse_schematic(&lines.join("\n"), &editable.mode);
+        let parts = map_parts(&schematic, &editable.neighbourhood);
+        (
+            parts.iter().flat_map(Part::get_numbers).sum(),
+            sum_of_gear_ratios(&parts, &editable.rule),
+        )
+    }
+
+    fn example() -> EditableSchematic {
+        EditableSchematic::new(
+            EXAMPLE,
+            ParseMode::default(),
+            Neighbourhood::EightConnected,
+            GearRule::default(),
+        )
+    }
+
+    #[test]
+    fn test_editable_schematic_new() {
+        let editable = example();
+
+        assert_eq!(editable.sum_of_part_numbers(), 4361);
+        assert_eq!(editable.sum_of_gear_ratios(), Some(467835));
+    }
+
+    #[test]
+    fn test_editable_schematic_set_symbol() {
+        let mut editable = example();
+
+        // a symbol next to 114 and 58 makes them part numbers
+        editable.set((1, 8), '#').unwrap();
+        editable.set((4, 8), '*').unwrap();
+        assert_eq!(editable.sum_of_part_numbers(), 4361 + 114 + 633 + 58);
+        assert_eq!(full_sums(&editable).0, editable.sum_of_part_numbers());
+
+        // the '*' next to 617 becomes a gear
+        editable.set((3, 2), '5').unwrap();
+        assert_eq!(editable.sum_of_gear_ratios(), Some(467835 + 617 * 5));
+        assert_eq!(
+            full_sums(&editable),
+            (4361 + 114 + 633 + 58 + 5, Some(467835 + 617 * 5))
+        );
+    }
+
+    #[test]
+    fn test_editable_schematic_clear() {
+        let mut editable = example();
+
+        // splitting 467 in two leaves only 67 next to the gear
+        editable.clear((0, 0)).unwrap();
+        assert_eq!(editable.sum_of_part_numbers(), 4361 - 467 + 67);
+        assert_eq!(
+            editable.sum_of_gear_ratios(),
+            Some(467835 - 467 * 35 + 67 * 35)
+        );
+
+        // joining 35 to the left turns it into 35 with an extra digit in front
+        editable.set((2, 1), '1').unwrap();
+        assert_eq!(editable.sum_of_part_numbers(), 4361 - 467 + 67 - 35 + 135);
+
+        // removing the gear entirely
+        editable.clear((8, 5)).unwrap();
+        assert_eq!(
+            full_sums(&editable),
+            (
+                editable.sum_of_part_numbers(),
+                editable.sum_of_gear_ratios()
+            )
+        );
+        assert_eq!(editable.sum_of_gear_ratios(), Some(67 * 135));
+    }
+
+    #[test]
+    fn test_editable_schematic_grows() {
+        let mut editable = example();
+
+        editable.set((10, 12), '7').unwrap();
+        editable.set((11, 11), '+').unwrap();
+        assert_eq!(editable.sum_of_part_numbers(), 4361 + 7);
+        assert_eq!(full_sums(&editable).0, 4361 + 7);
+    }
+
+    #[test]
+    fn test_editable_schematic_gear_ratio_too_big() {
+        let mut editable = EditableSchematic::new(
+            "3000000000*2000000000\n.....................\n3000000000.2000000000",
+            ParseMode::default(),
+            Neighbourhood::EightConnected,
+            GearRule::default(),
+        );
+        assert_eq!(editable.sum_of_gear_ratios(), Some(6000000000000000000));
+
+        // a second gear takes the sum past i64::MAX, taking it away brings the sum back
+        editable.set((2, 10), '*').unwrap();
+        assert_eq!(editable.sum_of_gear_ratios(), None);
+        assert_eq!(full_sums(&editable).1, None);
+        editable.clear((0, 10)).unwrap();
+        assert_eq!(editable.sum_of_gear_ratios(), Some(6000000000000000000));
+
+        // a gear with a ratio past i64::MAX on its own
+        editable.set((2, 0), '9').unwrap();
+        assert_eq!(editable.sum_of_gear_ratios(), None);
+        editable.clear((2, 10)).unwrap();
+        assert_eq!(editable.sum_of_gear_ratios(), Some(0));
+    }
+
+    #[test]
+    fn test_editable_schematic_set_before_start() {
+        let mut editable = example();
+
+        assert_eq!(editable.set((-1, 0), '*'), Err((-1, 0)));
+        assert_eq!(editable.clear((3, -1)), Err((3, -1)));
+        assert_eq!(editable.lines.len(), 10);
+        assert_eq!(editable.sum_of_part_numbers(), 4361);
+    }
+
+    #[test]
+    fn test_editable_schematic_random_edits() {
+        let mut rng = XorShift::new(39);
+        let characters = ['.', '.', '.', '1', '2', '5', '9', '*', '#', '-', '+'];
+
+        for neighbourhood in [Neighbourhood::EightConnected, Neighbourhood::Manhattan(2)] {
+            let mut editable = EditableSchematic::new(
+                EXAMPLE,
+                ParseMode {
+                    negative_numbers: true,
+                    tokens: vec!["-+".to_string()],
+                },
+                neighbourhood,
+                GearRule::default(),
+            );
+
+            for _ in 0..300 {
+                let point = (rng.between(0, 10) as i32, rng.between(0, 10) as i32);
+                let ch = characters[rng.between(0, characters.len() as u64 - 1) as usize];
+                editable.set(point, ch).unwrap();
+
+                assert_eq!(
+                    full_sums(&editable),
+                    (
+                        editable.sum_of_part_numbers(),
+                        editable.sum_of_gear_ratios()
+                    )
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,247 @@
+/// Draw the engine schematic with the part numbers, the numbers that aren't part numbers, the
+/// symbols and the gears highlighted, either with ANSI colours for the terminal or as an HTML page.
+use super::{Cell, GearRule, Part, Point, Schematic};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Format {
+    Ansi,
+    Html,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Highlight {
+    /// An empty cell, drawn as '.'.
+    Empty,
+    /// A number adjacent to a symbol, counted in the sum of part numbers.
+    PartNumber,
+    /// A number not adjacent to any symbol.
+    Number,
+    Symbol,
+    /// A gear, with its gear ratio, `None` when it is too big to count.
+    Gear(Option<i64>),
+}
+
+impl Highlight {
+    fn ansi_colour(&self) -> Option<&'static str> {
+        match self {
+            Highlight::Empty => None,
+            Highlight::PartNumber => Some("\x1b[32m"),
+            Highlight::Number => Some("\x1b[31m"),
+            Highlight::Symbol => Some("\x1b[33m"),
+            Highlight::Gear(_) => Some("\x1b[1;35m"),
+        }
+    }
+
+    fn html_class(&self) -> Option<&'static str> {
+        match self {
+            Highlight::Empty => None,
+            Highlight::PartNumber => Some("part-number"),
+            Highlight::Number => Some("number"),
+            Highlight::Symbol => Some("symbol"),
+            Highlight::Gear(_) => Some("gear"),
+        }
+    }
+}
+
+const ANSI_RESET: &str = "\x1b[0m";
+
+const HTML_STYLE: &str = "\
+    <style>\n\
+    .part-number { color: green; }\n\
+    .number { color: red; }\n\
+    .symbol { color: goldenrod; }\n\
+    .gear { color: magenta; font-weight: bold; }\n\
+    </style>";
+
+/// The amount of lines and columns of the schematic in the input, the schematic itself doesn't
+/// know about any empty cells at the end of the lines.
+pub(super) fn size(input: &str) -> (usize, usize) {
+    let lines = input.lines().count();
+    let columns = input.lines().map(|line| line.chars().count()).max();
+    (lines, columns.unwrap_or(0))
+}
+
+pub(super) fn render(
+    schematic: &Schematic,
+    parts: &[Part],
+    rule: &GearRule,
+    (lines, columns): (usize, usize),
+    format: Format,
+) -> String {
+    let grid = highlight_grid(schematic, parts, rule, (lines, columns));
+
+    let mut gears: Vec<(Point, &Part, Option<i64>)> = parts
+        .iter()
+        .filter_map(|part| Some((part.position, part, rule.ratio(part)?)))
+        .collect();
+    gears.sort_by_key(|(position, _, _)| *position);
+
+    let mut output = String::new();
+    if format == Format::Html {
+        output.push_str(HTML_STYLE);
+        output.push_str("\n<pre>\n");
+    }
+
+    for row in grid.iter() {
+        // draw runs of cells with the same highlight together
+        let mut cells = row.iter().peekable();
+        while let Some(&(ch, highlight)) = cells.next() {
+            let mut run = ch.to_string();
+            while let Some((ch, _)) = cells.next_if(|(_, next)| *next == highlight) {
+                run.push(*ch);
+            }
+            output.push_str(&match format {
+                Format::Ansi => draw_ansi(&run, highlight),
+                Format::Html => draw_html(&run, highlight),
+            });
+        }
+        output.push('\n');
+    }
+
+    if format == Format::Html {
+        output.push_str("</pre>\n<ul>\n");
+    }
+    for ((x, y), gear, ratio) in gears {
+        let line = format!(
+            "gear at line {}, column {}: {} = {}",
+            x + 1,
+            y + 1,
+            rule.combine.show(&gear.get_numbers()),
+            show_ratio(ratio)
+        );
+        match format {
+            Format::Ansi => output.push_str(&format!("{}\n", line)),
+            Format::Html => output.push_str(&format!("<li>{}</li>\n", line)),
+        }
+    }
+    if format == Format::Html {
+        output.push_str("</ul>\n");
+    }
+
+    output
+}
+
+fn show_ratio(ratio: Option<i64>) -> String {
+    match ratio {
+        Some(ratio) => ratio.to_string(),
+        None => "too big to count".to_string(),
+    }
+}
+
+fn draw_ansi(run: &str, highlight: Highlight) -> String {
+    match highlight.ansi_colour() {
+        Some(colour) => format!("{}{}{}", colour, run, ANSI_RESET),
+        None => run.to_string(),
+    }
+}
+
+fn draw_html(run: &str, highlight: Highlight) -> String {
+    let escaped = run
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    match (highlight, highlight.html_class()) {
+        (Highlight::Gear(ratio), Some(class)) => format!(
+            "<span class=\"{}\" title=\"gear ratio {}\">{}</span>",
+            class,
+            show_ratio(ratio),
+            escaped
+        ),
+        (_, Some(class)) => format!("<span class=\"{}\">{}</span>", class, escaped),
+        (_, None) => escaped,
+    }
+}
+
+/// Lay out the schematic as a grid of characters with their highlights.
+fn highlight_grid(
+    schematic: &Schematic,
+    parts: &[Part],
+    rule: &GearRule,
+    (lines, columns): (usize, usize),
+) -> Vec<Vec<(char, Highlight)>> {
+    // numbers are counted when they're adjacent to a part, they're known by their start
+    let counted: HashSet<Point> = parts
+        .iter()
+        .flat_map(|part| part.numbers.iter().map(|number| number.start))
+        .collect();
+    let parts: HashMap<Point, &Part> = parts.iter().map(|part| (part.position, part)).collect();
+
+    let mut grid = vec![vec![('.', Highlight::Empty); columns]; lines];
+
+    for (&point, cell) in schematic.iter() {
+        let highlight = match (cell, parts.get(&point)) {
+            (Cell::Number { .. }, _) => match counted.contains(&point) {
+                true => Highlight::PartNumber,
+                false => Highlight::Number,
+            },
+            (Cell::Symbol(_), Some(part)) => match rule.ratio(part) {
+                Some(ratio) => Highlight::Gear(ratio),
+                None => Highlight::Symbol,
+            },
+            (Cell::Symbol(_), None) => Highlight::Symbol,
+        };
+
+        let (x, y) = point;
+        for (i, ch) in cell.text().chars().enumerate() {
+            if let Some(cell) = grid
+                .get_mut(x as usize)
+                .and_then(|line| line.get_mut(y as usize + i))
+            {
+                *cell = (ch, highlight);
+            }
+        }
+    }
+
+    grid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::EXAMPLE;
+    use super::super::{map_parts, parse_schematic, Neighbourhood, ParseMode};
+    use super::*;
+
+    #[test]
+    fn test_render_ansi() {
+        let schematic = parse_schematic(EXAMPLE, &ParseMode::default());
+        let parts = map_parts(&schematic, &Neighbourhood::EightConnected);
+
+        let rendered = render(
+            &schematic,
+            &parts,
+            &GearRule::default(),
+            size(EXAMPLE),
+            Format::Ansi,
+        );
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        assert_eq!(lines[0], "\x1b[32m467\x1b[0m..\x1b[31m114\x1b[0m..");
+        assert_eq!(lines[1], "...\x1b[1;35m*\x1b[0m......");
+        assert_eq!(lines[4], "\x1b[32m617\x1b[0m\x1b[33m*\x1b[0m......");
+        assert_eq!(lines[10], "gear at line 2, column 4: 467 * 35 = 16345");
+        assert_eq!(lines[11], "gear at line 9, column 6: 755 * 598 = 451490");
+        assert_eq!(lines.len(), 12);
+    }
+
+    #[test]
+    fn test_render_html() {
+        let input = "12.&\n.*..\n..34";
+        let schematic = parse_schematic(input, &ParseMode::default());
+        let parts = map_parts(&schematic, &Neighbourhood::EightConnected);
+
+        let rendered = render(
+            &schematic,
+            &parts,
+            &GearRule::default(),
+            size(input),
+            Format::Html,
+        );
+
+        assert!(rendered.contains(
+            "<span class=\"part-number\">12</span>.<span class=\"symbol\">&amp;</span>\n"
+        ));
+        assert!(rendered.contains(".<span class=\"gear\" title=\"gear ratio 408\">*</span>..\n"));
+        assert!(rendered.contains("<li>gear at line 2, column 2: 12 * 34 = 408</li>"));
+    }
+}
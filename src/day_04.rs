@@ -101,52 +101,186 @@
 ///
 /// Process all of the original and copied scratchcards until no more scratchcards are won.
 /// Including the original set of scratchcards, how many total scratchcards do you end up with?
+use crate::random::XorShift;
 use nom::bytes::complete::tag;
 use nom::character::complete;
-use nom::character::complete::{char, digit1, multispace1, newline};
+use nom::character::complete::{char, multispace1, newline};
 use nom::multi::separated_list0;
-use nom::sequence::{preceded, separated_pair, tuple};
+use nom::sequence::{delimited, separated_pair, tuple};
 use nom::IResult;
-use std::collections::HashSet;
-use std::iter;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::RangeInclusive;
+
+mod trace;
 
 const INPUT: &str = include_str!("../input/day_04");
 
-pub fn run() {
+pub fn run(options: &[String]) {
+    // `--generate 100` prints that many cards winning as many copies as they can instead of
+    // solving, `--seed` picks other numbers
+    if let Some(amount) = crate::option_value(options, "--generate") {
+        let amount: u32 = amount.parse().expect("the amount should be a number");
+        let seed = crate::option_value(options, "--seed").map_or(2023, |seed| {
+            seed.parse().expect("the seed should be a number")
+        });
+        let mut rng = XorShift::new(seed);
+        for id in 1..=amount {
+            let matches = (amount - id).min(10) as usize;
+            println!("{}", adversarial_scratchcard(&mut rng, id, matches));
+        }
+        return;
+    }
+
     let (_, scratchcards) =
         separated_list0(newline, Scratchcard::parse)(INPUT).expect("parsing input failed");
 
-    let sum_of_score: u32 = scratchcards.iter().map(Scratchcard::score).sum();
-    println!(
-        "The total amount of points that all the scratchcards are worth is: {}",
-        sum_of_score
-    );
-
-    let total_amount_scratchcards = amount_of_scratchcards_won(&scratchcards);
-    println!(
-        "The total amount of scratchcards that you end up with is: {}",
-        total_amount_scratchcards
-    );
+    for duplicate in find_duplicate_numbers(&scratchcards) {
+        println!("Warning: {}", duplicate);
+    }
+
+    // a number listed more than once on a card only counts once by default, `--duplicates count`
+    // counts every time it's listed
+    let scratchcards: Vec<Scratchcard> = match crate::option_value(options, "--duplicates") {
+        None | Some("collapse") => scratchcards.iter().map(Scratchcard::collapse).collect(),
+        Some("count") => scratchcards,
+        Some(other) => panic!("unknown way to handle duplicate numbers: {}", other),
+    };
+
+    // the cards score like the puzzle's by default, `--scoring linear`, `--scoring exponential:3`
+    // or `--scoring weights:17=5,48=2` change how the matches are scored
+    let scoring = match crate::option_value(options, "--scoring") {
+        Some(scoring) => Scoring::parse(scoring).expect("invalid scoring"),
+        None => Scoring::default(),
+    };
+    let sum_of_score = scratchcards.iter().try_fold(0_u32, |sum, scratchcard| {
+        sum.checked_add(scoring.score(scratchcard)?)
+    });
+    match sum_of_score {
+        Some(sum_of_score) => println!(
+            "The total amount of points that all the scratchcards are worth is: {}",
+            sum_of_score
+        ),
+        None => println!(
+            "The total amount of points that all the scratchcards are worth is too big to count"
+        ),
+    }
+
+    for problem in validate_ids(&scratchcards) {
+        println!("Warning: {}", problem);
+    }
+    let cards = index_by_id(&scratchcards)
+        .unwrap_or_else(|id| panic!("card {} is in the pile more than once", id));
+
+    // the cards win copies of the cards below them by default, `--copies previous` or
+    // `--copies wrap` change which cards they win
+    let rule = match crate::option_value(options, "--copies") {
+        Some(rule) => CopyRule::parse(rule).expect("invalid copy rule"),
+        None => CopyRule::Next,
+    };
+
+    match total_scratchcards(&cards, rule) {
+        Some(total_amount_scratchcards) => println!(
+            "The total amount of scratchcards that you end up with is: {}",
+            total_amount_scratchcards
+        ),
+        None => {
+            println!("The total amount of scratchcards that you end up with is too big to count")
+        }
+    }
+
+    // `--trace table` or `--trace dot` shows how the cards won each other, the latter as a
+    // Graphviz graph
+    if let Some(format) = crate::option_value(options, "--trace") {
+        let Some(trace) = trace::trace(&cards, rule) else {
+            println!("The cascade has too many scratchcards to trace");
+            return;
+        };
+        match format {
+            "table" => {
+                print!("{}", trace.table());
+                println!(
+                    "The cascade is {} generations deep and ends with {} scratchcards",
+                    trace.depth(),
+                    trace.total()
+                );
+            }
+            "dot" => print!("{}", trace.dot()),
+            other => panic!("unknown trace format: {}", other),
+        }
+    }
+}
+
+/// Numbers with the amount of times they're listed.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Multiset(BTreeMap<u32, u32>);
+
+impl FromIterator<u32> for Multiset {
+    fn from_iter<I: IntoIterator<Item = u32>>(iter: I) -> Multiset {
+        let mut multiset = Multiset::default();
+        for number in iter {
+            *multiset.0.entry(number).or_insert(0) += 1;
+        }
+        multiset
+    }
+}
+
+impl Multiset {
+    /// The numbers in both, as many times as the least of the two lists them.
+    fn intersection(&self, other: &Multiset) -> Multiset {
+        Multiset(
+            self.0
+                .iter()
+                .filter_map(|(number, &count)| Some((*number, count.min(*other.0.get(number)?))))
+                .collect(),
+        )
+    }
+
+    /// Every number only once.
+    fn collapse(&self) -> Multiset {
+        self.0.keys().copied().collect()
+    }
+
+    /// The numbers listed more than once, with the amount of times they're listed.
+    fn duplicates(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.0
+            .iter()
+            .filter(|(_, &count)| count > 1)
+            .map(|(&number, &count)| (number, count))
+    }
+
+    /// Every number, as many times as it's listed.
+    fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.0
+            .iter()
+            .flat_map(|(&number, &count)| std::iter::repeat_n(number, count as usize))
+    }
 }
 
+/// A scratchcard, with the numbers as they are listed. A number can be listed more than once, a
+/// match is then a winning number paired up with a number you have; every listing can only be
+/// paired up once. Collapse the card to count every number only once.
 #[derive(Debug, PartialEq)]
 struct Scratchcard {
-    winning_numbers: HashSet<u32>,
-    numbers: HashSet<u32>,
+    id: u32,
+    winning_numbers: Multiset,
+    numbers: Multiset,
 }
 
 impl Scratchcard {
     fn parse(input: &str) -> IResult<&str, Scratchcard> {
-        fn set_of_numbers(input: &str) -> IResult<&str, HashSet<u32>> {
+        fn set_of_numbers(input: &str) -> IResult<&str, Multiset> {
             let (input, vec) = separated_list0(multispace1, complete::u32)(input)?;
             Ok((input, vec.into_iter().collect()))
         }
 
+        let (input, id) = delimited(
+            tuple((tag("Card"), multispace1)),
+            complete::u32,
+            tuple((tag(":"), multispace1)),
+        )(input)?;
         let (input, (winning_numbers, numbers)) = separated_pair(
-            preceded(
-                tuple((tag("Card"), multispace1, digit1, tag(":"), multispace1)),
-                set_of_numbers,
-            ),
+            set_of_numbers,
             tuple((multispace1, char('|'), multispace1)),
             set_of_numbers,
         )(input)?;
@@ -154,55 +288,418 @@ impl Scratchcard {
         Ok((
             input,
             Scratchcard {
+                id,
                 winning_numbers,
                 numbers,
             },
         ))
     }
 
-    fn score(&self) -> u32 {
-        let amount_won_with = self.amount_won() as u32;
-        match amount_won_with {
-            0 => 0,
-            1.. => 2_u32.pow(amount_won_with - 1),
+    /// The same card with every number listed only once.
+    fn collapse(&self) -> Scratchcard {
+        Scratchcard {
+            id: self.id,
+            winning_numbers: self.winning_numbers.collapse(),
+            numbers: self.numbers.collapse(),
         }
     }
 
+    #[cfg(test)]
+    fn score(&self) -> Option<u32> {
+        Scoring::default().score(self)
+    }
+
     fn amount_won(&self) -> usize {
-        self.winning_numbers.intersection(&self.numbers).count()
+        self.matches().len()
     }
+
+    /// The numbers you have that are winning numbers, once for every match.
+    fn matches(&self) -> Vec<u32> {
+        self.winning_numbers
+            .intersection(&self.numbers)
+            .iter()
+            .collect()
+    }
+}
+
+impl fmt::Display for Multiset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let numbers: Vec<String> = self.iter().map(|number| format!("{:>2}", number)).collect();
+        write!(f, "{}", numbers.join(" "))
+    }
+}
+
+impl fmt::Display for Scratchcard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Card {:>3}: {} | {}",
+            self.id, self.winning_numbers, self.numbers
+        )
+    }
+}
+
+/// A number listed more than once on a card.
+#[derive(Debug, PartialEq)]
+struct DuplicateNumber {
+    id: u32,
+    /// Whether it's listed more than once among the winning numbers or the numbers you have.
+    winning: bool,
+    number: u32,
+    count: u32,
 }
 
-fn amount_of_scratchcards_won(scratchcards: &Vec<Scratchcard>) -> u32 {
-    // initialize a co-vector for the scratchcards to keep track of the cards on the pile,
-    // start with 1 of each card
-    let mut pile: Vec<u32> = iter::repeat(1).take(scratchcards.len()).collect();
-
-    // run through all the scratchcards to count the winnings
-    for (i, scratchcard) in scratchcards.iter().enumerate() {
-        // for the 'x' of numbers won with, add the next 'x' of scratchcards to the pile
-        for x in (i + 1)..=(i + scratchcard.amount_won()) {
-            // get the amount of the current card, this will be the amount of next cards won
-            if let Some(&this_card_amount) = pile.get(i) {
-                // add these amounts to the next 'x' cards
-                if let Some(card_amount) = pile.get_mut(x) {
-                    *card_amount += this_card_amount;
+impl fmt::Display for DuplicateNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "card {} lists {} {} times among the {}",
+            self.id,
+            self.number,
+            self.count,
+            match self.winning {
+                true => "winning numbers",
+                false => "numbers you have",
+            }
+        )
+    }
+}
+
+/// Find all the numbers listed more than once on the cards.
+fn find_duplicate_numbers(scratchcards: &[Scratchcard]) -> Vec<DuplicateNumber> {
+    scratchcards
+        .iter()
+        .flat_map(|scratchcard| {
+            [
+                (true, &scratchcard.winning_numbers),
+                (false, &scratchcard.numbers),
+            ]
+            .into_iter()
+            .flat_map(move |(winning, numbers)| {
+                numbers
+                    .duplicates()
+                    .map(move |(number, count)| DuplicateNumber {
+                        id: scratchcard.id,
+                        winning,
+                        number,
+                        count,
+                    })
+            })
+        })
+        .collect()
+}
+
+/// How many points a card is worth for its matches.
+#[derive(Debug, Clone, PartialEq)]
+enum Scoring {
+    /// A point for every match.
+    Linear,
+    /// A point for the first match, multiplied by the base for every match after it.
+    Exponential(u32),
+    /// The weights of all the matched numbers added up, a number without a weight is worth 1.
+    Weights(HashMap<u32, u32>),
+}
+
+impl Default for Scoring {
+    /// The puzzle's scoring, doubling the points for every match after the first.
+    fn default() -> Scoring {
+        Scoring::Exponential(2)
+    }
+}
+
+impl Scoring {
+    /// Parse a scoring like `linear`, `exponential:3` or `weights:17=5,48=2`.
+    fn parse(input: &str) -> Option<Scoring> {
+        match input.split_once(':') {
+            None if input == "linear" => Some(Scoring::Linear),
+            None if input == "exponential" => Some(Scoring::default()),
+            Some(("exponential", base)) => Some(Scoring::Exponential(base.parse().ok()?)),
+            Some(("weights", weights)) => {
+                let weights = weights
+                    .split(',')
+                    .map(|weight| {
+                        let (number, weight) = weight.split_once('=')?;
+                        Some((number.trim().parse().ok()?, weight.trim().parse().ok()?))
+                    })
+                    .collect::<Option<_>>()?;
+                Some(Scoring::Weights(weights))
+            }
+            _ => None,
+        }
+    }
+
+    /// The points the card is worth, or `None` when they don't fit in a `u32`.
+    fn score(&self, scratchcard: &Scratchcard) -> Option<u32> {
+        match self {
+            Scoring::Linear => u32::try_from(scratchcard.amount_won()).ok(),
+            Scoring::Exponential(base) => match u32::try_from(scratchcard.amount_won()).ok()? {
+                0 => Some(0),
+                matches => base.checked_pow(matches - 1),
+            },
+            Scoring::Weights(weights) => scratchcard
+                .matches()
+                .into_iter()
+                .map(|number| weights.get(&number).copied().unwrap_or(1))
+                .try_fold(0_u32, |sum, weight| sum.checked_add(weight)),
+        }
+    }
+}
+
+/// Which cards a card wins copies of, as many as it has matches.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CopyRule {
+    /// The cards below it, the puzzle's rule.
+    Next,
+    /// The cards above it, closest first.
+    Previous,
+    /// The cards below it, going on at the top of the pile past the end of it.
+    WrapAround,
+}
+
+impl CopyRule {
+    /// Parse a copy rule like `next`, `previous` or `wrap`.
+    fn parse(input: &str) -> Option<CopyRule> {
+        match input {
+            "next" => Some(CopyRule::Next),
+            "previous" => Some(CopyRule::Previous),
+            "wrap" => Some(CopyRule::WrapAround),
+            _ => None,
+        }
+    }
+
+    /// The ids of the cards won by the card with `matches`, given the highest id on the pile. Ids
+    /// of missing cards are in there too, they just can't be won.
+    fn won(&self, id: u32, matches: u32, highest: u32) -> Vec<u32> {
+        match self {
+            CopyRule::Next => (1..=matches).map_while(|x| id.checked_add(x)).collect(),
+            CopyRule::Previous => (id.saturating_sub(matches)..id).rev().collect(),
+            // a card 0 wins the cards from 1 up, with only a card 0 there's nothing to win
+            CopyRule::WrapAround if highest == 0 => Vec::new(),
+            CopyRule::WrapAround => (1..=matches)
+                .map(|x| ((u64::from(id) + u64::from(x) - 1) % u64::from(highest)) as u32 + 1)
+                .collect(),
+        }
+    }
+
+    /// The order the cards are scratched in, so the copies a card wins are on the pile before it's
+    /// scratched. When the cards wrap around, the copies of cards that have already been scratched
+    /// are put on the pile but don't win anything anymore.
+    fn order(&self, cards: &BTreeMap<u32, &Scratchcard>) -> Vec<u32> {
+        match self {
+            CopyRule::Next | CopyRule::WrapAround => cards.keys().copied().collect(),
+            CopyRule::Previous => cards.keys().rev().copied().collect(),
+        }
+    }
+}
+
+/// Something wrong with the card ids in the pile, the ids should count up from 1 without skipping.
+#[derive(Debug, PartialEq)]
+enum IdProblem {
+    /// A card has id 0, while the ids count from 1.
+    Zero,
+    /// No card has these ids, while cards with higher ids exist.
+    Missing(RangeInclusive<u32>),
+    /// More than one card has this id.
+    Duplicated(u32),
+    /// The card comes after a card with a higher id.
+    OutOfOrder { id: u32, after: u32 },
+}
+
+impl fmt::Display for IdProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IdProblem::Zero => write!(f, "card 0 comes before card 1, the ids count from 1"),
+            IdProblem::Missing(ids) if ids.start() == ids.end() => {
+                write!(f, "card {} is missing", ids.start())
+            }
+            IdProblem::Missing(ids) => {
+                write!(f, "cards {} to {} are missing", ids.start(), ids.end())
+            }
+            IdProblem::Duplicated(id) => write!(f, "card {} is in the pile more than once", id),
+            IdProblem::OutOfOrder { id, after } => {
+                write!(f, "card {} comes after card {}", id, after)
+            }
+        }
+    }
+}
+
+/// Find all the problems with the card ids, in the order of the ids.
+fn validate_ids(scratchcards: &[Scratchcard]) -> Vec<IdProblem> {
+    let mut problems = Vec::new();
+
+    let mut counts: BTreeMap<u32, usize> = BTreeMap::new();
+    for scratchcard in scratchcards.iter() {
+        *counts.entry(scratchcard.id).or_default() += 1;
+    }
+    // walk the gaps between the ids, the first id should be 1
+    let mut previous = 0;
+    for (&id, &count) in counts.iter() {
+        if id == 0 {
+            problems.push(IdProblem::Zero);
+        } else if id > previous + 1 {
+            problems.push(IdProblem::Missing((previous + 1)..=(id - 1)));
+        }
+        if count > 1 {
+            problems.push(IdProblem::Duplicated(id));
+        }
+        previous = id;
+    }
+
+    let mut highest_so_far = 0;
+    for scratchcard in scratchcards.iter() {
+        if scratchcard.id < highest_so_far {
+            problems.push(IdProblem::OutOfOrder {
+                id: scratchcard.id,
+                after: highest_so_far,
+            });
+        }
+        highest_so_far = highest_so_far.max(scratchcard.id);
+    }
+
+    problems.sort_by_key(|problem| match problem {
+        IdProblem::Zero => 0,
+        IdProblem::Missing(ids) => *ids.start(),
+        IdProblem::Duplicated(id) | IdProblem::OutOfOrder { id, .. } => *id,
+    });
+    problems
+}
+
+/// The scratchcards ordered by their id, or the first id that more than one card has.
+fn index_by_id(scratchcards: &[Scratchcard]) -> Result<BTreeMap<u32, &Scratchcard>, u32> {
+    let mut cards = BTreeMap::new();
+    for scratchcard in scratchcards.iter() {
+        if cards.insert(scratchcard.id, scratchcard).is_some() {
+            return Err(scratchcard.id);
+        }
+    }
+    Ok(cards)
+}
+
+#[cfg(test)]
+fn amount_of_scratchcards_won(cards: &BTreeMap<u32, &Scratchcard>) -> Option<u128> {
+    amount_of_scratchcards_won_with_rule(cards, CopyRule::Next, None)
+}
+
+/// Count the scratchcards by putting the copies on the pile card by card, the straightforward way
+/// to check `total_scratchcards` against. Where the copies come from is written down in the trace
+/// when there is one. Returns `None` when the amounts don't fit in a `u128`.
+fn amount_of_scratchcards_won_with_rule(
+    cards: &BTreeMap<u32, &Scratchcard>,
+    rule: CopyRule,
+    mut trace: Option<&mut trace::Trace>,
+) -> Option<u128> {
+    // keep track of the cards on the pile by their id, start with 1 of each card
+    let mut pile: BTreeMap<u32, u128> = cards.keys().map(|&id| (id, 1)).collect();
+    let highest = cards.keys().last().copied().unwrap_or(0);
+
+    // run through all the scratchcards in the order of the rule to count the winnings, a card's
+    // amount is final by the time it's reached
+    for id in rule.order(cards) {
+        let this_card_amount = pile[&id];
+        // for the 'x' of numbers won with, add 'x' of scratchcards to the pile, a missing card
+        // can't be won
+        for x in rule.won(id, cards[&id].amount_won() as u32, highest) {
+            if let Some(card_amount) = pile.get_mut(&x) {
+                *card_amount = card_amount.checked_add(this_card_amount)?;
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.record(id, x, this_card_amount)?;
                 }
             }
         }
     }
 
-    pile.iter().sum()
+    let total = pile
+        .values()
+        .try_fold(0_u128, |total, &amount| total.checked_add(amount))?;
+    if let Some(trace) = trace {
+        trace.counts = pile;
+    }
+    Some(total)
+}
+
+/// Count the scratchcards in a single pass from the last card scratched back to the first. A single
+/// instance of a card ends up as itself and everything its copies win, which only depends on the
+/// cards scratched after it; a copy of a card that has been scratched already is just itself.
+/// Returns `None` when the total doesn't fit in a `u128`.
+fn total_scratchcards(cards: &BTreeMap<u32, &Scratchcard>, rule: CopyRule) -> Option<u128> {
+    let highest = cards.keys().last().copied().unwrap_or(0);
+    let order = rule.order(cards);
+    let positions: HashMap<u32, usize> = order
+        .iter()
+        .enumerate()
+        .map(|(position, &id)| (id, position))
+        .collect();
+
+    // the amount of cards a single instance of every card ends up as
+    let mut instances: HashMap<u32, u128> = HashMap::new();
+    for (position, &id) in order.iter().enumerate().rev() {
+        let mut amount: u128 = 1;
+        for x in rule.won(id, cards[&id].amount_won() as u32, highest) {
+            let won = match positions.get(&x) {
+                None => continue,
+                Some(&later) if later > position => instances[&x],
+                Some(_) => 1,
+            };
+            amount = amount.checked_add(won)?;
+        }
+        instances.insert(id, amount);
+    }
+
+    instances
+        .values()
+        .try_fold(0_u128, |total, &amount| total.checked_add(amount))
+}
+
+/// Generate a card whose first `matches` winning numbers are all numbers you have, out of 10
+/// winning numbers and 25 numbers you have. With every card winning as many cards as it can, the
+/// amount of copies about doubles with every card.
+fn adversarial_scratchcard(rng: &mut XorShift, id: u32, matches: usize) -> Scratchcard {
+    let mut winning_numbers = Vec::new();
+    while winning_numbers.len() < 10 {
+        let number = rng.between(1, 99) as u32;
+        if !winning_numbers.contains(&number) {
+            winning_numbers.push(number);
+        }
+    }
+
+    let mut numbers: Vec<u32> = winning_numbers[..matches].to_vec();
+    while numbers.len() < 25 {
+        let number = rng.between(1, 99) as u32;
+        if !winning_numbers.contains(&number) && !numbers.contains(&number) {
+            numbers.push(number);
+        }
+    }
+
+    Scratchcard {
+        id,
+        winning_numbers: winning_numbers.into_iter().collect(),
+        numbers: numbers.into_iter().collect(),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    pub(super) const EXAMPLE: &str = "\
+        Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53\n\
+        Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19\n\
+        Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1\n\
+        Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83\n\
+        Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36\n\
+        Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
+
+    pub(super) fn parse_pile(input: &str) -> Vec<Scratchcard> {
+        separated_list0(newline, Scratchcard::parse)(input)
+            .unwrap()
+            .1
+    }
+
     #[test]
     fn test_scratchcard_parse_1() {
         let input = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53";
         let expected_scratchcard = Scratchcard {
+            id: 1,
             winning_numbers: vec![41, 48, 83, 86, 17].into_iter().collect(),
             numbers: vec![83, 86, 6, 31, 17, 9, 48, 53].into_iter().collect(),
         };
@@ -214,6 +711,7 @@ mod tests {
     fn test_scratchcard_parse_2() {
         let input = "Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19";
         let expected_scratchcard = Scratchcard {
+            id: 2,
             winning_numbers: vec![13, 32, 20, 16, 61].into_iter().collect(),
             numbers: vec![61, 30, 68, 82, 17, 32, 24, 19].into_iter().collect(),
         };
@@ -225,6 +723,7 @@ mod tests {
     fn test_scratchcard_parse_3() {
         let input = "Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1";
         let expected_scratchcard = Scratchcard {
+            id: 3,
             winning_numbers: vec![1, 21, 53, 59, 44].into_iter().collect(),
             numbers: vec![69, 82, 63, 72, 16, 21, 14, 1].into_iter().collect(),
         };
@@ -236,6 +735,7 @@ mod tests {
     fn test_scratchcard_parse_4() {
         let input = "Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83";
         let expected_scratchcard = Scratchcard {
+            id: 4,
             winning_numbers: vec![41, 92, 73, 84, 69].into_iter().collect(),
             numbers: vec![59, 84, 76, 51, 58, 5, 54, 83].into_iter().collect(),
         };
@@ -247,6 +747,7 @@ mod tests {
     fn test_scratchcard_parse_5() {
         let input = "Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36";
         let expected_scratchcard = Scratchcard {
+            id: 5,
             winning_numbers: vec![87, 83, 26, 28, 32].into_iter().collect(),
             numbers: vec![88, 30, 70, 12, 93, 22, 82, 36].into_iter().collect(),
         };
@@ -258,6 +759,7 @@ mod tests {
     fn test_scratchcard_parse_6() {
         let input = "Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
         let expected_scratchcard = Scratchcard {
+            id: 6,
             winning_numbers: vec![31, 18, 13, 56, 72].into_iter().collect(),
             numbers: vec![74, 77, 10, 23, 35, 67, 36, 11].into_iter().collect(),
         };
@@ -268,92 +770,382 @@ mod tests {
     #[test]
     fn test_scratchcard_score_1() {
         let scratchcard = Scratchcard {
+            id: 1,
             winning_numbers: vec![41, 48, 83, 86, 17].into_iter().collect(),
             numbers: vec![83, 86, 6, 31, 17, 9, 48, 53].into_iter().collect(),
         };
 
-        assert_eq!(scratchcard.score(), 8);
+        assert_eq!(scratchcard.score(), Some(8));
     }
 
     #[test]
     fn test_scratchcard_score_2() {
         let scratchcard = Scratchcard {
+            id: 2,
             winning_numbers: vec![13, 32, 20, 16, 61].into_iter().collect(),
             numbers: vec![61, 30, 68, 82, 17, 32, 24, 19].into_iter().collect(),
         };
 
-        assert_eq!(scratchcard.score(), 2);
+        assert_eq!(scratchcard.score(), Some(2));
     }
 
     #[test]
     fn test_scratchcard_score_3() {
         let scratchcard = Scratchcard {
+            id: 3,
             winning_numbers: vec![1, 21, 53, 59, 44].into_iter().collect(),
             numbers: vec![69, 82, 63, 72, 16, 21, 14, 1].into_iter().collect(),
         };
 
-        assert_eq!(scratchcard.score(), 2);
+        assert_eq!(scratchcard.score(), Some(2));
     }
 
     #[test]
     fn test_scratchcard_score_4() {
         let scratchcard = Scratchcard {
+            id: 4,
             winning_numbers: vec![41, 92, 73, 84, 69].into_iter().collect(),
             numbers: vec![59, 84, 76, 51, 58, 5, 54, 83].into_iter().collect(),
         };
 
-        assert_eq!(scratchcard.score(), 1);
+        assert_eq!(scratchcard.score(), Some(1));
     }
 
     #[test]
     fn test_scratchcard_score_5() {
         let scratchcard = Scratchcard {
+            id: 5,
             winning_numbers: vec![87, 83, 26, 28, 32].into_iter().collect(),
             numbers: vec![88, 30, 70, 12, 93, 22, 82, 36].into_iter().collect(),
         };
 
-        assert_eq!(scratchcard.score(), 0);
+        assert_eq!(scratchcard.score(), Some(0));
     }
 
     #[test]
     fn test_scratchcard_score_6() {
         let scratchcard = Scratchcard {
+            id: 6,
             winning_numbers: vec![31, 18, 13, 56, 72].into_iter().collect(),
             numbers: vec![74, 77, 10, 23, 35, 67, 36, 11].into_iter().collect(),
         };
 
-        assert_eq!(scratchcard.score(), 0);
+        assert_eq!(scratchcard.score(), Some(0));
     }
 
     #[test]
     fn test_amount_of_scratchcards_won() {
         let scratchcards = vec![
             Scratchcard {
+                id: 1,
                 winning_numbers: vec![41, 48, 83, 86, 17].into_iter().collect(),
                 numbers: vec![83, 86, 6, 31, 17, 9, 48, 53].into_iter().collect(),
             },
             Scratchcard {
+                id: 2,
                 winning_numbers: vec![13, 32, 20, 16, 61].into_iter().collect(),
                 numbers: vec![61, 30, 68, 82, 17, 32, 24, 19].into_iter().collect(),
             },
             Scratchcard {
+                id: 3,
                 winning_numbers: vec![1, 21, 53, 59, 44].into_iter().collect(),
                 numbers: vec![69, 82, 63, 72, 16, 21, 14, 1].into_iter().collect(),
             },
             Scratchcard {
+                id: 4,
                 winning_numbers: vec![41, 92, 73, 84, 69].into_iter().collect(),
                 numbers: vec![59, 84, 76, 51, 58, 5, 54, 83].into_iter().collect(),
             },
             Scratchcard {
+                id: 5,
                 winning_numbers: vec![87, 83, 26, 28, 32].into_iter().collect(),
                 numbers: vec![88, 30, 70, 12, 93, 22, 82, 36].into_iter().collect(),
             },
             Scratchcard {
+                id: 6,
                 winning_numbers: vec![31, 18, 13, 56, 72].into_iter().collect(),
                 numbers: vec![74, 77, 10, 23, 35, 67, 36, 11].into_iter().collect(),
             },
         ];
 
-        assert_eq!(amount_of_scratchcards_won(&scratchcards), 30);
+        let cards = index_by_id(&scratchcards).unwrap();
+
+        assert_eq!(amount_of_scratchcards_won(&cards), Some(30));
+    }
+
+    #[test]
+    fn test_validate_ids() {
+        let scratchcards = parse_pile(
+            "\
+            Card 1: 41 48 | 83 41\n\
+            Card 4: 13 32 | 61 30\n\
+            Card 2:  1 21 | 69 82\n\
+            Card 4: 87 83 | 88 30",
+        );
+
+        assert_eq!(
+            validate_ids(&scratchcards),
+            vec![
+                IdProblem::OutOfOrder { id: 2, after: 4 },
+                IdProblem::Missing(3..=3),
+                IdProblem::Duplicated(4),
+            ]
+        );
+        assert_eq!(index_by_id(&scratchcards), Err(4));
+    }
+
+    #[test]
+    fn test_validate_ids_gaps() {
+        let scratchcards = parse_pile(
+            "\
+            Card 0: 41 48 | 83 41\n\
+            Card 3: 13 32 | 61 30\n\
+            Card 4000000000: 1 21 | 69 82",
+        );
+
+        assert_eq!(
+            validate_ids(&scratchcards),
+            vec![
+                IdProblem::Zero,
+                IdProblem::Missing(1..=2),
+                IdProblem::Missing(4..=3999999999),
+            ]
+        );
+        assert_eq!(
+            IdProblem::Missing(4..=3999999999).to_string(),
+            "cards 4 to 3999999999 are missing"
+        );
+    }
+
+    #[test]
+    fn test_validate_ids_valid() {
+        let scratchcards = parse_pile("Card 1: 41 48 | 83 41\nCard 2: 13 32 | 61 30");
+
+        assert_eq!(validate_ids(&scratchcards), vec![]);
+    }
+
+    #[test]
+    fn test_amount_of_scratchcards_won_by_id() {
+        // card 1 wins cards 2 and 3, but there's no card 2, and card 3 comes first
+        let scratchcards = parse_pile(
+            "\
+            Card 3: 5 6 | 7 5\n\
+            Card 1: 1 2 | 1 2\n\
+            Card 4: 8 9 | 1 2",
+        );
+        let cards = index_by_id(&scratchcards).unwrap();
+
+        // 1 of card 1, 2 of card 3 and 1 + 2 of card 4
+        assert_eq!(amount_of_scratchcards_won(&cards), Some(6));
+    }
+
+    fn card_1() -> Scratchcard {
+        Scratchcard {
+            id: 1,
+            winning_numbers: vec![41, 48, 83, 86, 17].into_iter().collect(),
+            numbers: vec![83, 86, 6, 31, 17, 9, 48, 53].into_iter().collect(),
+        }
+    }
+
+    #[test]
+    fn test_scoring_parse() {
+        assert_eq!(Scoring::parse("linear"), Some(Scoring::Linear));
+        assert_eq!(Scoring::parse("exponential"), Some(Scoring::Exponential(2)));
+        assert_eq!(
+            Scoring::parse("exponential:3"),
+            Some(Scoring::Exponential(3))
+        );
+        assert_eq!(
+            Scoring::parse("weights:17=5,48=2"),
+            Some(Scoring::Weights(HashMap::from([(17, 5), (48, 2)])))
+        );
+        assert_eq!(Scoring::parse("weights:17"), None);
+        assert_eq!(Scoring::parse("quadratic"), None);
+    }
+
+    #[test]
+    fn test_scoring_score() {
+        let scratchcard = card_1();
+
+        assert_eq!(Scoring::default().score(&scratchcard), Some(8));
+        assert_eq!(Scoring::Linear.score(&scratchcard), Some(4));
+        assert_eq!(Scoring::Exponential(3).score(&scratchcard), Some(27));
+        // 17 and 48 are weighted, 83 and 86 are worth 1
+        assert_eq!(
+            Scoring::Weights(HashMap::from([(17, 5), (48, 2), (6, 100)])).score(&scratchcard),
+            Some(9)
+        );
+
+        // with 11 matches, 10 to the power of 10 doesn't fit
+        let scratchcard = Scratchcard {
+            id: 1,
+            winning_numbers: (1..=11).collect(),
+            numbers: (1..=11).collect(),
+        };
+        assert_eq!(Scoring::Exponential(10).score(&scratchcard), None);
+        assert_eq!(Scoring::Exponential(2).score(&scratchcard), Some(1024));
+    }
+
+    #[test]
+    fn test_copy_rule_won() {
+        assert_eq!(CopyRule::Next.won(3, 2, 6), vec![4, 5]);
+        assert_eq!(CopyRule::Next.won(u32::MAX - 1, 3, 6), vec![u32::MAX]);
+        assert_eq!(CopyRule::Previous.won(3, 4, 6), vec![2, 1, 0]);
+        assert_eq!(CopyRule::WrapAround.won(5, 3, 6), vec![6, 1, 2]);
+        assert_eq!(CopyRule::WrapAround.won(0, 2, 6), vec![1, 2]);
+        assert_eq!(CopyRule::WrapAround.won(0, 2, 0), vec![]);
+        assert_eq!(CopyRule::WrapAround.won(u32::MAX, 2, u32::MAX), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_amount_of_scratchcards_won_previous() {
+        let scratchcards = parse_pile(EXAMPLE);
+        let cards = index_by_id(&scratchcards).unwrap();
+
+        // card 4 wins card 3, the 2 cards 3 win cards 2 and 1, the 3 cards 2 win card 1
+        assert_eq!(
+            amount_of_scratchcards_won_with_rule(&cards, CopyRule::Previous, None),
+            Some(6 + 3 + 2 + 1 + 1 + 1)
+        );
+        // nothing wraps around in the example
+        assert_eq!(
+            amount_of_scratchcards_won_with_rule(&cards, CopyRule::WrapAround, None),
+            Some(30)
+        );
+    }
+
+    #[test]
+    fn test_amount_of_scratchcards_won_wrap_around() {
+        // card 2 wins a copy of card 1 and of itself, but they've been scratched already
+        let scratchcards = parse_pile("Card 1: 1 | 2\nCard 2: 1 2 | 1 2");
+        let cards = index_by_id(&scratchcards).unwrap();
+
+        assert_eq!(
+            amount_of_scratchcards_won_with_rule(&cards, CopyRule::WrapAround, None),
+            Some(4)
+        );
+    }
+
+    #[test]
+    fn test_scratchcard_duplicates() {
+        // 5 is a winning number twice and you have it three times, 7 is only a winning number once
+        let (_, scratchcard) = Scratchcard::parse("Card 1: 5 7 5 9 | 5 5 7 7 5").unwrap();
+
+        assert_eq!(scratchcard.matches(), vec![5, 5, 7]);
+        assert_eq!(scratchcard.amount_won(), 3);
+        assert_eq!(scratchcard.score(), Some(4));
+
+        let collapsed = scratchcard.collapse();
+        assert_eq!(collapsed.matches(), vec![5, 7]);
+        assert_eq!(collapsed.score(), Some(2));
+    }
+
+    #[test]
+    fn test_find_duplicate_numbers() {
+        let scratchcards = parse_pile(
+            "\
+            Card 1: 5 7 5 9 | 5 5 7 7 5\n\
+            Card 2: 1 2 3 | 4 5 6\n\
+            Card 3: 1 2 3 | 4 4 6",
+        );
+
+        assert_eq!(
+            find_duplicate_numbers(&scratchcards),
+            vec![
+                DuplicateNumber {
+                    id: 1,
+                    winning: true,
+                    number: 5,
+                    count: 2
+                },
+                DuplicateNumber {
+                    id: 1,
+                    winning: false,
+                    number: 5,
+                    count: 3
+                },
+                DuplicateNumber {
+                    id: 1,
+                    winning: false,
+                    number: 7,
+                    count: 2
+                },
+                DuplicateNumber {
+                    id: 3,
+                    winning: false,
+                    number: 4,
+                    count: 2
+                },
+            ]
+        );
+        assert_eq!(
+            find_duplicate_numbers(&scratchcards)[0].to_string(),
+            "card 1 lists 5 2 times among the winning numbers"
+        );
+    }
+
+    #[test]
+    fn test_total_scratchcards() {
+        let scratchcards = parse_pile(EXAMPLE);
+        let cards = index_by_id(&scratchcards).unwrap();
+
+        assert_eq!(total_scratchcards(&cards, CopyRule::Next), Some(30));
+        for rule in [CopyRule::Next, CopyRule::Previous, CopyRule::WrapAround] {
+            assert_eq!(
+                total_scratchcards(&cards, rule),
+                amount_of_scratchcards_won_with_rule(&cards, rule, None)
+            );
+        }
+
+        let scratchcards = parse_pile("Card 1: 1 | 2\nCard 2: 1 2 | 1 2");
+        let cards = index_by_id(&scratchcards).unwrap();
+        assert_eq!(total_scratchcards(&cards, CopyRule::WrapAround), Some(4));
+    }
+
+    fn adversarial_pile(amount: u32) -> Vec<Scratchcard> {
+        let mut rng = XorShift::new(44);
+        (1..=amount)
+            .map(|id| adversarial_scratchcard(&mut rng, id, (amount - id).min(10) as usize))
+            .collect()
+    }
+
+    #[test]
+    fn test_adversarial_scratchcard() {
+        let scratchcards = adversarial_pile(20);
+
+        let matches: Vec<usize> = scratchcards.iter().map(Scratchcard::amount_won).collect();
+        assert_eq!(matches[..10], [10; 10]);
+        assert_eq!(matches[10..], [9, 8, 7, 6, 5, 4, 3, 2, 1, 0]);
+        assert!(find_duplicate_numbers(&scratchcards).is_empty());
+
+        // the generated cards read back the same
+        let input: Vec<String> = scratchcards.iter().map(ToString::to_string).collect();
+        assert_eq!(parse_pile(&input.join("\n")), scratchcards);
+    }
+
+    #[test]
+    fn test_total_scratchcards_adversarial() {
+        // the amount about doubles with every card, a hundred cards still fit
+        let scratchcards = adversarial_pile(100);
+        let cards = index_by_id(&scratchcards).unwrap();
+        let total = total_scratchcards(&cards, CopyRule::Next).unwrap();
+        assert!(total > u64::MAX as u128);
+        assert_eq!(
+            amount_of_scratchcards_won_with_rule(&cards, CopyRule::Next, None),
+            Some(total)
+        );
+        assert_eq!(
+            trace::trace(&cards, CopyRule::Next).map(|trace| trace.total()),
+            Some(total)
+        );
+
+        let scratchcards = adversarial_pile(200);
+        let cards = index_by_id(&scratchcards).unwrap();
+        assert_eq!(total_scratchcards(&cards, CopyRule::Next), None);
+        assert_eq!(
+            amount_of_scratchcards_won_with_rule(&cards, CopyRule::Next, None),
+            None
+        );
+        assert_eq!(trace::trace(&cards, CopyRule::Next), None);
     }
 }
@@ -0,0 +1,157 @@
+/// Follow the cascade of won scratchcards: how many instances of every card end up on the pile,
+/// which cards won copies of which, and how many generations of winning it takes.
+use super::{amount_of_scratchcards_won_with_rule, CopyRule, Scratchcard};
+use std::collections::BTreeMap;
+
+#[derive(Debug, PartialEq)]
+pub(super) struct Trace {
+    /// The amount of instances of every card, by id, originals included.
+    pub(super) counts: BTreeMap<u32, u128>,
+    /// The amount of copies won, by the id of the winning card and the id of the card won.
+    pub(super) contributions: BTreeMap<(u32, u32), u128>,
+    /// How far down the cascade every card is, by id: 0 for a card that no card wins, otherwise one
+    /// more than the furthest card that wins it.
+    pub(super) generations: BTreeMap<u32, u32>,
+}
+
+impl Trace {
+    /// The amount of generations of the whole cascade.
+    pub(super) fn depth(&self) -> u32 {
+        self.generations.values().copied().max().unwrap_or(0)
+    }
+
+    pub(super) fn total(&self) -> u128 {
+        self.counts.values().sum()
+    }
+
+    /// Write down that the card `from` won copies of the card `to`. Returns `None` when the amount
+    /// of copies doesn't fit in a `u128`.
+    pub(super) fn record(&mut self, from: u32, to: u32, copies: u128) -> Option<()> {
+        let total = self.contributions.entry((from, to)).or_insert(0);
+        *total = total.checked_add(copies)?;
+        let generation = self.generations[&from] + 1;
+        let next_generation = self.generations.entry(to).or_insert(0);
+        *next_generation = (*next_generation).max(generation);
+        Some(())
+    }
+
+    /// A table with a line for every card, listing its instances, its generation and the cards it
+    /// got copies from.
+    pub(super) fn table(&self) -> String {
+        let mut output = String::from("card  instances  generation  copies from\n");
+        for (&id, count) in self.counts.iter() {
+            let from: Vec<String> = self
+                .contributions
+                .iter()
+                .filter(|((_, to), _)| *to == id)
+                .map(|((from, _), copies)| format!("{} ({})", from, copies))
+                .collect();
+            output.push_str(
+                format!(
+                    "{:>4}  {:>9}  {:>10}  {}",
+                    id,
+                    count,
+                    self.generations[&id],
+                    from.join(", ")
+                )
+                .trim_end(),
+            );
+            output.push('\n');
+        }
+        output
+    }
+
+    /// A Graphviz graph of every card pointing to the cards it wins, with the amount of copies.
+    pub(super) fn dot(&self) -> String {
+        let mut output = String::from("digraph cascade {\n");
+        for (id, count) in self.counts.iter() {
+            output.push_str(&format!(
+                "    {} [label=\"card {}\\n{} instances\"];\n",
+                id, id, count
+            ));
+        }
+        for ((from, to), copies) in self.contributions.iter() {
+            output.push_str(&format!("    {} -> {} [label=\"{}\"];\n", from, to, copies));
+        }
+        output.push_str("}\n");
+        output
+    }
+}
+
+/// Run through the cascade with `amount_of_scratchcards_won_with_rule`, writing down where the
+/// copies come from along the way. Returns `None` when the amounts don't fit in a `u128`.
+pub(super) fn trace(cards: &BTreeMap<u32, &Scratchcard>, rule: CopyRule) -> Option<Trace> {
+    let mut trace = Trace {
+        counts: BTreeMap::new(),
+        contributions: BTreeMap::new(),
+        generations: cards.keys().map(|&id| (id, 0)).collect(),
+    };
+    amount_of_scratchcards_won_with_rule(cards, rule, Some(&mut trace))?;
+    Some(trace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{parse_pile, EXAMPLE};
+    use super::super::{amount_of_scratchcards_won, index_by_id};
+    use super::*;
+
+    fn example_trace() -> Trace {
+        let scratchcards = parse_pile(EXAMPLE);
+        let cards = index_by_id(&scratchcards).unwrap();
+        let trace = trace(&cards, CopyRule::Next).unwrap();
+        assert_eq!(Some(trace.total()), amount_of_scratchcards_won(&cards));
+        trace
+    }
+
+    #[test]
+    fn test_trace() {
+        let trace = example_trace();
+
+        assert_eq!(
+            trace.counts,
+            BTreeMap::from([(1, 1), (2, 2), (3, 4), (4, 8), (5, 14), (6, 1)])
+        );
+        assert_eq!(
+            trace.contributions,
+            BTreeMap::from([
+                ((1, 2), 1),
+                ((1, 3), 1),
+                ((1, 4), 1),
+                ((1, 5), 1),
+                ((2, 3), 2),
+                ((2, 4), 2),
+                ((3, 4), 4),
+                ((3, 5), 4),
+                ((4, 5), 8),
+            ])
+        );
+        assert_eq!(
+            trace.generations,
+            BTreeMap::from([(1, 0), (2, 1), (3, 2), (4, 3), (5, 4), (6, 0)])
+        );
+        assert_eq!(trace.depth(), 4);
+        assert_eq!(trace.total(), 30);
+    }
+
+    #[test]
+    fn test_trace_table() {
+        let table = example_trace().table();
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert_eq!(lines[0], "card  instances  generation  copies from");
+        assert_eq!(lines[1], "   1          1           0");
+        assert_eq!(lines[4], "   4          8           3  1 (1), 2 (2), 3 (4)");
+        assert_eq!(lines.len(), 7);
+    }
+
+    #[test]
+    fn test_trace_dot() {
+        let dot = example_trace().dot();
+
+        assert!(dot.starts_with("digraph cascade {\n"));
+        assert!(dot.contains("    5 [label=\"card 5\\n14 instances\"];\n"));
+        assert!(dot.contains("    4 -> 5 [label=\"8\"];\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+}
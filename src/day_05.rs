@@ -155,19 +155,92 @@
 ///
 /// Consider all of the initial seed numbers listed in the ranges on the first line of the almanac.
 /// What is the lowest location number that corresponds to any of the initial seed numbers?
-use nom::branch::alt;
+use crate::interval_set::IntervalSet;
+use crate::random::XorShift;
 use nom::bytes::complete::tag;
 use nom::character::complete;
-use nom::character::complete::{newline, space1};
+use nom::character::complete::{alpha1, newline, space1};
 use nom::multi::separated_list1;
-use nom::sequence::{delimited, preceded, tuple};
+use nom::sequence::{delimited, preceded, separated_pair, terminated, tuple};
 use nom::IResult;
 use std::ops::Range;
 
+mod compose;
+mod lookup;
+mod validate;
+
 const INPUT: &str = include_str!("../input/day_05");
 
-pub fn run() {
-    let (_, almanac) = Almanac::parse(INPUT).expect("parsing failed");
+pub fn run(options: &[String]) {
+    // `--benchmark 5000,1000` times the ways to look up the locations on a random almanac with
+    // that many entries in every map and that many seeds, `--seed` picks another almanac
+    if let Some(sizes) = crate::option_value(options, "--benchmark") {
+        let (entries, seeds) = sizes
+            .split_once(',')
+            .expect("the sizes should look like 5000,1000");
+        let seed = crate::option_value(options, "--seed").map_or(2023, |seed| {
+            seed.parse().expect("the seed should be a number")
+        });
+        let almanac = lookup::random_almanac(
+            &mut XorShift::new(seed),
+            entries
+                .parse()
+                .expect("the amount of entries should be a number"),
+            seeds
+                .parse()
+                .expect("the amount of seeds should be a number"),
+        );
+        for (lookup, duration) in lookup::benchmark(&almanac) {
+            println!("{:?} lookup took {:?}", lookup, duration);
+        }
+        return;
+    }
+
+    let (_, mut almanac) = Almanac::parse(INPUT).expect("parsing failed");
+
+    // any overlapping, empty or overflowing map entries are reported and rejected by default,
+    // `--precedence first` or `--precedence last` picks the entry to use where they overlap
+    let problems = validate::validate_maps(&almanac);
+    for problem in problems.iter() {
+        println!("Warning: {}", problem);
+    }
+    match crate::option_value(options, "--precedence") {
+        None if !problems.is_empty() => panic!("the almanac has problems with its maps"),
+        None | Some("first") => almanac.apply_precedence(validate::Precedence::FirstEntry),
+        Some("last") => almanac.apply_precedence(validate::Precedence::LastEntry),
+        Some(other) => panic!("unknown precedence: {}", other),
+    }
+
+    // `--compose seed,location` prints the maps in between composed into a single map
+    if let Some(categories) = crate::option_value(options, "--compose") {
+        let (from, to) = categories
+            .split_once(',')
+            .expect("compose should look like seed,location");
+        match almanac.compose(from, to) {
+            Some(composed) => print!("{}", composed),
+            None => println!("There are no maps from {} to {}", from, to),
+        }
+    }
+
+    // `--query soil,81,humidity` maps a number from one category to another, `--query
+    // humidity,78,soil` lists all the numbers that map to it
+    if let Some(query) = crate::option_value(options, "--query") {
+        let &[from, n, to] = query.split(',').collect::<Vec<_>>().as_slice() else {
+            panic!("a query should look like soil,81,humidity");
+        };
+        let n = n.parse().expect("the number should be a number");
+        // a query can go back up the chain as well, from location to seed for example
+        match (
+            almanac.map_between(from, to, n),
+            almanac.unmap_between(from, to, n),
+        ) {
+            (Some(mapped), _) => println!("{} {} corresponds to {} {}", from, n, to, mapped),
+            (None, Some(unmapped)) => {
+                println!("{} {} corresponds to {} {:?}", from, n, to, unmapped)
+            }
+            (None, None) => println!("There are no maps from {} to {}", from, to),
+        }
+    }
 
     let locations = almanac.get_locations();
     let lowest_location = locations
@@ -179,19 +252,52 @@ pub fn run() {
     let location_ranges = almanac.get_locations_from_seed_ranges();
     let lowest_location_in_seed_ranges = location_ranges
         .first()
-        .expect("there was no lowest location in the seed ranges")
-        .start;
+        .expect("there was no lowest location in the seed ranges");
     println!(
         "The lowest location number from seed ranges is: {}",
         lowest_location_in_seed_ranges
     );
+
+    // `--check` finds the lowest location again by searching up from the lowest location
+    if options.iter().any(|option| option == "--check") {
+        let searched = almanac
+            .lowest_location_searching_up()
+            .expect("there was no lowest location searching up");
+        assert_eq!(
+            searched, lowest_location_in_seed_ranges,
+            "searching up finds another lowest location"
+        );
+        println!(
+            "Searching up from the lowest location finds the same lowest location: {}",
+            searched
+        );
+    }
+
+    // `--inverse 0,100` finds all the seeds that end up at locations 0 up to 100
+    if let Some(range) = crate::option_value(options, "--inverse") {
+        let (start, end) = range
+            .split_once(',')
+            .expect("the locations should look like 0,100");
+        let locations = start.parse().expect("the start should be a number")
+            ..end.parse().expect("the end should be a number");
+        let seeds = almanac
+            .preimage_between("seed", "location", locations.clone())
+            .expect("there are no maps from seed to location");
+        println!(
+            "The {} seeds that end up at locations {:?} are: {:?}",
+            seeds.len(),
+            locations,
+            seeds.ranges()
+        );
+    }
 }
 
 #[derive(Debug, PartialEq)]
 struct Almanac {
     seeds: Vec<u64>,
-    seed_ranges: Vec<Range<u64>>,
-    maps: Vec<Vec<AlmanacMapEntry>>,
+    seed_ranges: IntervalSet,
+    /// The maps in the order of the file, follow their categories to find the chain of maps.
+    maps: Vec<AlmanacMap>,
 }
 
 impl Almanac {
@@ -210,24 +316,7 @@ impl Almanac {
             })
             .collect();
 
-        let almanac_map = preceded(
-            tuple((
-                alt((
-                    tag("seed-to-soil"),
-                    tag("soil-to-fertilizer"),
-                    tag("fertilizer-to-water"),
-                    tag("water-to-light"),
-                    tag("light-to-temperature"),
-                    tag("temperature-to-humidity"),
-                    tag("humidity-to-location"),
-                )),
-                tag(" map:"),
-                newline,
-            )),
-            separated_list1(newline, AlmanacMapEntry::parse),
-        );
-
-        let (input, maps) = separated_list1(tuple((newline, newline)), almanac_map)(input)?;
+        let (input, maps) = separated_list1(tuple((newline, newline)), AlmanacMap::parse)(input)?;
 
         Ok((
             input,
@@ -239,90 +328,129 @@ impl Almanac {
         ))
     }
 
+    /// The maps to go through, one after the other, to get from one category to another. Only the
+    /// category itself is reached without any maps.
+    fn chain(&self, from: &str, to: &str) -> Option<Vec<&AlmanacMap>> {
+        let mut chain = Vec::new();
+        let mut category = from;
+        while category != to {
+            // every map can only be used once, otherwise the categories go round in circles
+            if chain.len() == self.maps.len() {
+                return None;
+            }
+            let map = self.maps.iter().find(|map| map.source == category)?;
+            chain.push(map);
+            category = &map.destination;
+        }
+        Some(chain)
+    }
+
+    /// Map a number from one category to another, following the chain of maps in between.
+    fn map_between(&self, from: &str, to: &str, n: u64) -> Option<u64> {
+        Some(self.chain(from, to)?.iter().fold(n, |n, map| map.map(n)))
+    }
+
     fn get_locations(&self) -> Vec<u64> {
-        self.seeds
-            .iter()
-            .map(|seed| {
-                // start with the seed number, and go through all the almanac maps in the list
-                let mut n = *seed;
-                for map in self.maps.iter() {
-                    // look for a match in the entries of the almanac map
-                    n = match map.iter().filter_map(|entry| entry.map(&n)).next() {
-                        // either an entry is found in the almanac map
-                        Some(mapped) => mapped,
-                        // or it remains the same number
-                        None => n,
-                    }
-                }
-                return n;
-            })
-            .collect()
+        // compose the maps once, every seed is a single lookup after that
+        let composed = self
+            .compose("seed", "location")
+            .expect("there are no maps from seed to location");
+        self.seeds.iter().map(|seed| composed.map(*seed)).collect()
     }
 
-    fn get_locations_from_seed_ranges(&self) -> Vec<Range<u64>> {
+    fn get_locations_from_seed_ranges(&self) -> IntervalSet {
         let mut inputs = self.seed_ranges.clone();
-        let mut outputs = Vec::new();
-
-        // go over every map, translating all the inputs into outputs
-        for map in self.maps.iter() {
-            for entry in map.iter() {
-                // map all the input ranges into output ranges, any remaining input ranges are used
-                // for the next map entry
-                let (mapped, remainder): (Vec<_>, Vec<_>) = inputs
-                    .into_iter()
-                    .map(|input| entry.map_range(input))
-                    .unzip();
-                inputs = remainder.into_iter().flat_map(|i| i).collect();
-                outputs.append(&mut mapped.into_iter().filter_map(|o| o).collect());
-            }
-            // remaining input ranges stay as they are, add all the outputs ranges for the next map
-            inputs.append(&mut outputs);
-            // sort the ranges
-            inputs.sort_by_key(|range| range.start);
-            // collapse ranges together if the end and start overlap, this is not really needed
-            inputs = inputs.into_iter().fold(Vec::new(), |mut vec, next_range| {
-                match vec.pop() {
-                    Some(range) => match range.end == next_range.start {
-                        true => {
-                            // the ranges should be merged
-                            vec.push(range.start..next_range.end);
-                        }
-                        false => {
-                            // the ranges are separate
-                            vec.push(range);
-                            vec.push(next_range);
-                        }
-                    },
-                    None => {
-                        vec.push(next_range);
-                    }
+
+        // go over every map from seed to location, translating all the inputs into outputs
+        let chain = self
+            .chain("seed", "location")
+            .expect("there are no maps from seed to location");
+        for map in chain {
+            let mut outputs = IntervalSet::new();
+            for entry in map.entries.iter() {
+                if inputs.is_empty() {
+                    break;
                 }
-                return vec;
-            });
+                // map the inputs into outputs, whatever remains of the inputs is used for the next
+                // map entry
+                let (mapped, remainder) = entry.map_range(&inputs);
+                outputs = outputs.union(&mapped);
+                inputs = remainder;
+            }
+            // remaining inputs stay as they are, together with the outputs for the next map
+            inputs = inputs.union(&outputs);
         }
         inputs
     }
 }
 
+/// A map from one category to another, like the seed-to-soil map.
+#[derive(Debug, PartialEq)]
+struct AlmanacMap {
+    source: String,
+    destination: String,
+    /// The entries in the order of the file, until `apply_precedence` sorts them by their source.
+    entries: Vec<AlmanacMapEntry>,
+}
+
+impl AlmanacMap {
+    fn parse(input: &str) -> IResult<&str, AlmanacMap> {
+        let (input, (source, destination)) = terminated(
+            separated_pair(alpha1, tag("-to-"), alpha1),
+            tuple((tag(" map:"), newline)),
+        )(input)?;
+        let (input, entries) = separated_list1(newline, AlmanacMapEntry::parse)(input)?;
+
+        Ok((
+            input,
+            AlmanacMap {
+                source: source.to_string(),
+                destination: destination.to_string(),
+                entries,
+            },
+        ))
+    }
+
+    /// Map a number with the entry it's in, a number that isn't in any entry stays the same. The
+    /// entries have to be sorted by their source without overlapping, like `apply_precedence`
+    /// leaves them, to find the entry with a binary search.
+    fn map(&self, n: u64) -> u64 {
+        // the entry with the last start at or before the number
+        let i = self
+            .entries
+            .partition_point(|entry| entry.source.start <= n);
+        i.checked_sub(1)
+            .and_then(|i| self.entries[i].map(&n))
+            .unwrap_or(n)
+    }
+}
+
 #[derive(Debug, PartialEq)]
 struct AlmanacMapEntry {
     destination: Range<u64>,
     source: Range<u64>,
+    /// Whether the entry is written with a size going past `u64::MAX`, and cut short to end there.
+    overflowed: bool,
 }
 
 impl AlmanacMapEntry {
+    /// Parse an entry, an entry that goes past `u64::MAX` is cut short to end there.
     fn parse(input: &str) -> IResult<&str, AlmanacMapEntry> {
-        let (input, (destination_start, source_start, range_size)) = tuple((
+        let (input, (destination_start, source_start, size)) = tuple((
             complete::u64,
             preceded(space1, complete::u64),
             preceded(space1, complete::u64),
         ))(input)?;
 
+        let range_size = size
+            .min(u64::MAX - destination_start)
+            .min(u64::MAX - source_start);
         Ok((
             input,
             AlmanacMapEntry {
                 destination: destination_start..destination_start + range_size,
                 source: source_start..source_start + range_size,
+                overflowed: range_size < size,
             },
         ))
     }
@@ -335,34 +463,15 @@ impl AlmanacMapEntry {
         Some(self.destination.start + (n - self.source.start))
     }
 
-    fn map_range(&self, input: Range<u64>) -> (Option<Range<u64>>, Vec<Range<u64>>) {
-        // map the start and the last number in the range (boundary - 1)
-        match (self.map(&input.start), self.map(&(input.end - 1))) {
-            // the input range is fully contained, there is no remainder
-            (Some(mapped_start), Some(mapped_end)) => (Some(mapped_start..mapped_end + 1), vec![]),
-            // only the start can be mapped, the end is outside of the entry
-            // the destination end correlates to the source's end, so no need to map
-            (Some(mapped_start), None) => (
-                Some(mapped_start..self.destination.end),
-                vec![self.source.end..input.end],
-            ),
-            // only the end can be mapped, the start is outside of the entry
-            // the destination start correlates to the source's start, so no need to map
-            (None, Some(mapped_end)) => (
-                Some(self.destination.start..mapped_end + 1),
-                vec![input.start..self.source.start],
-            ),
-            // the input range isn't in source at all, return the input as remainder
-            (None, None) => {
-                if input.start < self.source.start && input.end > self.source.end {
-                    return (
-                        Some(self.destination.clone()),
-                        vec![input.start..self.source.start, self.source.end..input.end],
-                    );
-                }
-                (None, vec![input])
-            }
-        }
+    /// Split the input up into the numbers that are mapped by the entry, where they end up, and
+    /// the remainder that the entry doesn't map.
+    fn map_range(&self, input: &IntervalSet) -> (IntervalSet, IntervalSet) {
+        let source = IntervalSet::from(self.source.clone());
+        let offset = self.destination.start as i128 - self.source.start as i128;
+        (
+            input.intersection(&source).translate(offset),
+            input.difference(&source),
+        )
     }
 }
 
@@ -378,6 +487,7 @@ mod tests {
         let expected_entry = AlmanacMapEntry {
             destination: 50..52,
             source: 98..100,
+            overflowed: false,
         };
 
         let (remainder, actual_entry) = AlmanacMapEntry::parse(input).unwrap();
@@ -393,6 +503,7 @@ mod tests {
         let expected_entry = AlmanacMapEntry {
             destination: 0..1,
             source: 69..70,
+            overflowed: false,
         };
 
         let (remainder, actual_entry) = AlmanacMapEntry::parse(input).unwrap();
@@ -408,6 +519,7 @@ mod tests {
         let expected_entry = AlmanacMapEntry {
             destination: 1..70,
             source: 0..69,
+            overflowed: false,
         };
 
         let (remainder, actual_entry) = AlmanacMapEntry::parse(input).unwrap();
@@ -416,97 +528,143 @@ mod tests {
         assert_eq!(remainder, "");
     }
 
-    fn example_almanac() -> Almanac {
+    pub(super) fn example_almanac() -> Almanac {
         Almanac {
             seeds: vec![79, 14, 55, 13],
-            seed_ranges: vec![79..93, 55..68],
+            seed_ranges: IntervalSet::from_iter([79..93, 55..68]),
             maps: vec![
-                vec![
-                    AlmanacMapEntry {
-                        destination: 50..52,
-                        source: 98..100,
-                    },
-                    AlmanacMapEntry {
-                        destination: 52..100,
-                        source: 50..98,
-                    },
-                ],
-                vec![
-                    AlmanacMapEntry {
-                        destination: 0..37,
-                        source: 15..52,
-                    },
-                    AlmanacMapEntry {
-                        destination: 37..39,
-                        source: 52..54,
-                    },
-                    AlmanacMapEntry {
-                        destination: 39..54,
-                        source: 0..15,
-                    },
-                ],
-                vec![
-                    AlmanacMapEntry {
-                        destination: 49..57,
-                        source: 53..61,
-                    },
-                    AlmanacMapEntry {
-                        destination: 0..42,
-                        source: 11..53,
-                    },
-                    AlmanacMapEntry {
-                        destination: 42..49,
-                        source: 0..7,
-                    },
-                    AlmanacMapEntry {
-                        destination: 57..61,
-                        source: 7..11,
-                    },
-                ],
-                vec![
-                    AlmanacMapEntry {
-                        destination: 88..95,
-                        source: 18..25,
-                    },
-                    AlmanacMapEntry {
-                        destination: 18..88,
-                        source: 25..95,
-                    },
-                ],
-                vec![
-                    AlmanacMapEntry {
-                        destination: 45..68,
-                        source: 77..100,
-                    },
-                    AlmanacMapEntry {
-                        destination: 81..100,
-                        source: 45..64,
-                    },
-                    AlmanacMapEntry {
-                        destination: 68..81,
-                        source: 64..77,
-                    },
-                ],
-                vec![
-                    AlmanacMapEntry {
-                        destination: 0..1,
-                        source: 69..70,
-                    },
-                    AlmanacMapEntry {
-                        destination: 1..70,
-                        source: 0..69,
-                    },
-                ],
-                vec![
-                    AlmanacMapEntry {
-                        destination: 60..97,
-                        source: 56..93,
-                    },
-                    AlmanacMapEntry {
-                        destination: 56..60,
-                        source: 93..97,
-                    },
-                ],
+                AlmanacMap {
+                    source: "seed".to_string(),
+                    destination: "soil".to_string(),
+                    entries: vec![
+                        AlmanacMapEntry {
+                            destination: 50..52,
+                            source: 98..100,
+                            overflowed: false,
+                        },
+                        AlmanacMapEntry {
+                            destination: 52..100,
+                            source: 50..98,
+                            overflowed: false,
+                        },
+                    ],
+                },
+                AlmanacMap {
+                    source: "soil".to_string(),
+                    destination: "fertilizer".to_string(),
+                    entries: vec![
+                        AlmanacMapEntry {
+                            destination: 0..37,
+                            source: 15..52,
+                            overflowed: false,
+                        },
+                        AlmanacMapEntry {
+                            destination: 37..39,
+                            source: 52..54,
+                            overflowed: false,
+                        },
+                        AlmanacMapEntry {
+                            destination: 39..54,
+                            source: 0..15,
+                            overflowed: false,
+                        },
+                    ],
+                },
+                AlmanacMap {
+                    source: "fertilizer".to_string(),
+                    destination: "water".to_string(),
+                    entries: vec![
+                        AlmanacMapEntry {
+                            destination: 49..57,
+                            source: 53..61,
+                            overflowed: false,
+                        },
+                        AlmanacMapEntry {
+                            destination: 0..42,
+                            source: 11..53,
+                            overflowed: false,
+                        },
+                        AlmanacMapEntry {
+                            destination: 42..49,
+                            source: 0..7,
+                            overflowed: false,
+                        },
+                        AlmanacMapEntry {
+                            destination: 57..61,
+                            source: 7..11,
+                            overflowed: false,
+                        },
+                    ],
+                },
+                AlmanacMap {
+                    source: "water".to_string(),
+                    destination: "light".to_string(),
+                    entries: vec![
+                        AlmanacMapEntry {
+                            destination: 88..95,
+                            source: 18..25,
+                            overflowed: false,
+                        },
+                        AlmanacMapEntry {
+                            destination: 18..88,
+                            source: 25..95,
+                            overflowed: false,
+                        },
+                    ],
+                },
+                AlmanacMap {
+                    source: "light".to_string(),
+                    destination: "temperature".to_string(),
+                    entries: vec![
+                        AlmanacMapEntry {
+                            destination: 45..68,
+                            source: 77..100,
+                            overflowed: false,
+                        },
+                        AlmanacMapEntry {
+                            destination: 81..100,
+                            source: 45..64,
+                            overflowed: false,
+                        },
+                        AlmanacMapEntry {
+                            destination: 68..81,
+                            source: 64..77,
+                            overflowed: false,
+                        },
+                    ],
+                },
+                AlmanacMap {
+                    source: "temperature".to_string(),
+                    destination: "humidity".to_string(),
+                    entries: vec![
+                        AlmanacMapEntry {
+                            destination: 0..1,
+                            source: 69..70,
+                            overflowed: false,
+                        },
+                        AlmanacMapEntry {
+                            destination: 1..70,
+                            source: 0..69,
+                            overflowed: false,
+                        },
+                    ],
+                },
+                AlmanacMap {
+                    source: "humidity".to_string(),
+                    destination: "location".to_string(),
+                    entries: vec![
+                        AlmanacMapEntry {
+                            destination: 60..97,
+                            source: 56..93,
+                            overflowed: false,
+                        },
+                        AlmanacMapEntry {
+                            destination: 56..60,
+                            source: 93..97,
+                            overflowed: false,
+                        },
+                    ],
+                },
             ],
         }
     }
@@ -561,7 +719,10 @@ mod tests {
     #[test]
     fn test_almanac_get_locations_from_seed_ranges() {
         let actual_location_ranges = example_almanac().get_locations_from_seed_ranges();
-        assert_eq!(actual_location_ranges, vec![46..61, 82..85, 86..90, 94..99]);
+        assert_eq!(
+            actual_location_ranges.ranges(),
+            &[46..61, 82..85, 86..90, 94..99]
+        );
     }
 
     #[test]
@@ -569,8 +730,12 @@ mod tests {
         let entry = AlmanacMapEntry {
             destination: 18..88,
             source: 25..95,
+            overflowed: false,
         };
-        assert_eq!(entry.map_range(81..95), (Some(74..88), vec![]))
+        assert_eq!(
+            entry.map_range(&IntervalSet::from(81..95)),
+            (IntervalSet::from(74..88), IntervalSet::new())
+        )
     }
 
     #[test]
@@ -578,10 +743,58 @@ mod tests {
         let entry = AlmanacMapEntry {
             destination: 10..20,
             source: 40..50,
+            overflowed: false,
         };
         assert_eq!(
-            entry.map_range(35..55),
-            (Some(10..20), vec![35..40, 50..55])
+            entry.map_range(&IntervalSet::from(35..55)),
+            (
+                IntervalSet::from(10..20),
+                IntervalSet::from_iter([35..40, 50..55])
+            )
         )
     }
+
+    #[test]
+    fn test_almanac_chain() {
+        let almanac = example_almanac();
+
+        let chain = almanac.chain("soil", "humidity").unwrap();
+        let categories: Vec<&str> = chain.iter().map(|map| map.source.as_str()).collect();
+        assert_eq!(
+            categories,
+            vec!["soil", "fertilizer", "water", "light", "temperature"]
+        );
+        assert!(almanac.chain("water", "water").unwrap().is_empty());
+        assert_eq!(almanac.chain("location", "seed"), None);
+        assert_eq!(almanac.chain("seed", "colour"), None);
+    }
+
+    #[test]
+    fn test_almanac_map_between() {
+        let mut almanac = example_almanac();
+        almanac.apply_precedence(validate::Precedence::FirstEntry);
+
+        // seed 79 is soil 81, fertilizer 81, water 81, light 74, temperature 78, humidity 78
+        assert_eq!(almanac.map_between("soil", "humidity", 81), Some(78));
+        assert_eq!(almanac.map_between("seed", "location", 79), Some(82));
+        assert_eq!(almanac.map_between("light", "light", 74), Some(74));
+        assert_eq!(almanac.map_between("humidity", "soil", 78), None);
+    }
+
+    #[test]
+    fn test_almanac_follows_names() {
+        // the maps are resolved by their categories, not by their order in the file
+        let input = "\
+        seeds: 79 14\n\
+        \n\
+        soil-to-location map:\n\
+        0 80 10\n\
+        \n\
+        seed-to-soil map:\n\
+        81 79 1";
+        let (_, almanac) = Almanac::parse(input).unwrap();
+
+        assert_eq!(almanac.get_locations(), vec![1, 14]);
+        assert_eq!(almanac.map_between("soil", "location", 85), Some(5));
+    }
 }
@@ -0,0 +1,452 @@
+/// Compose the maps of a chain into a single map straight from the first category to the last. The
+/// pieces of the composed map are sorted and don't overlap, and together they cover every number,
+/// so a lookup is a single binary search. Going the other way, they tell which numbers end up in a
+/// range.
+use super::{Almanac, AlmanacMap, AlmanacMapEntry};
+use crate::interval_set::IntervalSet;
+use std::fmt;
+use std::ops::Range;
+
+/// A map as pieces sorted by their source, without overlaps or gaps, covering all the numbers from
+/// 0 up to `u64::MAX`. A number that stays the same is in a piece with the same source and
+/// destination.
+#[derive(Debug, PartialEq)]
+pub(super) struct PiecewiseMap {
+    pub(super) source: String,
+    pub(super) destination: String,
+    pub(super) pieces: Vec<AlmanacMapEntry>,
+}
+
+impl PiecewiseMap {
+    pub(super) fn map(&self, n: u64) -> u64 {
+        // the piece with the last start at or before the number
+        let i = self.pieces.partition_point(|piece| piece.source.start <= n);
+        match i.checked_sub(1).and_then(|i| self.pieces[i].map(&n)) {
+            Some(mapped) => mapped,
+            // only u64::MAX itself isn't covered
+            None => n,
+        }
+    }
+
+    /// The map going through this map and then through the next one.
+    pub(super) fn then(&self, next: &PiecewiseMap) -> PiecewiseMap {
+        let mut pieces = Vec::new();
+        for piece in self.pieces.iter() {
+            // cut the piece up where its destination crosses the pieces of the next map
+            let destination = &piece.destination;
+            let first = next
+                .pieces
+                .partition_point(|next_piece| next_piece.source.end <= destination.start);
+            for next_piece in next.pieces[first..]
+                .iter()
+                .take_while(|next_piece| next_piece.source.start < destination.end)
+            {
+                let overlap = destination.start.max(next_piece.source.start)
+                    ..destination.end.min(next_piece.source.end);
+                let source = piece.source.start + (overlap.start - destination.start)
+                    ..piece.source.start + (overlap.end - destination.start);
+                let destination = next_piece.restrict(overlap).destination;
+                pieces.push(AlmanacMapEntry {
+                    destination,
+                    source,
+                    overflowed: false,
+                });
+            }
+        }
+
+        PiecewiseMap {
+            source: self.source.clone(),
+            destination: next.destination.clone(),
+            pieces: merge(pieces),
+        }
+    }
+
+    /// All the numbers that map into the range, as sorted ranges. More than one number can map to
+    /// the same number, so there can be more numbers than the range holds.
+    pub(super) fn preimage(&self, range: Range<u64>) -> IntervalSet {
+        self.pieces
+            .iter()
+            .filter_map(|piece| {
+                let overlap =
+                    range.start.max(piece.destination.start)..range.end.min(piece.destination.end);
+                if overlap.is_empty() {
+                    return None;
+                }
+                Some(piece.inverse().restrict(overlap).destination)
+            })
+            .collect()
+    }
+
+    /// Walk up through the pieces in the order of their destination, the first piece with a
+    /// source in the numbers holds the lowest number they map to. As more than one piece can map
+    /// onto the same numbers, the walk goes on until no lower number can be found.
+    pub(super) fn lowest_from(&self, numbers: &IntervalSet) -> Option<u64> {
+        let mut pieces: Vec<&AlmanacMapEntry> = self.pieces.iter().collect();
+        pieces.sort_by_key(|piece| piece.destination.start);
+
+        let mut lowest: Option<u64> = None;
+        for piece in pieces {
+            if lowest.is_some_and(|lowest| lowest <= piece.destination.start) {
+                break;
+            }
+            let first = numbers
+                .intersection(&IntervalSet::from(piece.source.clone()))
+                .first();
+            if let Some(mapped) = first.and_then(|first| piece.map(&first)) {
+                lowest = Some(lowest.map_or(mapped, |lowest| lowest.min(mapped)));
+            }
+        }
+        lowest
+    }
+}
+
+impl fmt::Display for PiecewiseMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}-to-{} map:", self.source, self.destination)?;
+        for piece in self.pieces.iter() {
+            write!(
+                f,
+                "{} {:?} -> {} {:?}",
+                self.source, piece.source, self.destination, piece.destination
+            )?;
+            if piece.source == piece.destination {
+                write!(f, " (unchanged)")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl AlmanacMapEntry {
+    /// The entry going the other way, from its destination back to its source.
+    pub(super) fn inverse(&self) -> AlmanacMapEntry {
+        AlmanacMapEntry {
+            destination: self.source.clone(),
+            source: self.destination.clone(),
+            overflowed: self.overflowed,
+        }
+    }
+
+    /// The part of the entry for a range within its source.
+    fn restrict(&self, range: Range<u64>) -> AlmanacMapEntry {
+        let start = self.destination.start + (range.start - self.source.start);
+        AlmanacMapEntry {
+            destination: start..start + (range.end - range.start),
+            source: range,
+            overflowed: self.overflowed,
+        }
+    }
+}
+
+impl AlmanacMap {
+    /// The entries cut down to the numbers that no earlier entry covers, so the first one is used
+    /// where they overlap, and sorted by their source. Entries without any numbers are left out.
+    pub(super) fn sorted_entries(&self) -> Vec<AlmanacMapEntry> {
+        let mut pieces: Vec<AlmanacMapEntry> = Vec::new();
+        let mut covered = IntervalSet::new();
+        for entry in self.entries.iter() {
+            // only the parts of the entry that no earlier entry covers
+            let remaining = IntervalSet::from(entry.source.clone()).difference(&covered);
+            covered.insert(entry.source.clone());
+            pieces.extend(
+                remaining
+                    .ranges()
+                    .iter()
+                    .map(|range| entry.restrict(range.clone())),
+            );
+        }
+        pieces.sort_by_key(|piece| piece.source.start);
+        pieces
+    }
+
+    /// The map as sorted pieces. Where entries overlap the first one is used, and the numbers that
+    /// aren't in any entry stay the same.
+    pub(super) fn normalise(&self) -> PiecewiseMap {
+        // fill the gaps with pieces that keep the numbers the same
+        let mut filled = Vec::new();
+        let mut start = 0;
+        for piece in self.sorted_entries() {
+            if start < piece.source.start {
+                filled.push(AlmanacMapEntry {
+                    destination: start..piece.source.start,
+                    source: start..piece.source.start,
+                    overflowed: false,
+                });
+            }
+            start = piece.source.end;
+            filled.push(piece);
+        }
+        if start < u64::MAX {
+            filled.push(AlmanacMapEntry {
+                destination: start..u64::MAX,
+                source: start..u64::MAX,
+                overflowed: false,
+            });
+        }
+
+        PiecewiseMap {
+            source: self.source.clone(),
+            destination: self.destination.clone(),
+            pieces: merge(filled),
+        }
+    }
+
+    /// All the numbers that map to the number: those in an entry that maps them to it, as long as
+    /// no earlier entry takes them, and the number itself when it isn't in any entry.
+    pub(super) fn unmap(&self, n: u64) -> Vec<u64> {
+        // the entries end before u64::MAX, so it can only come from itself
+        if n == u64::MAX {
+            return vec![n];
+        }
+        self.normalise()
+            .preimage(n..n + 1)
+            .ranges()
+            .iter()
+            .flat_map(|range| range.clone())
+            .collect()
+    }
+}
+
+impl Almanac {
+    /// Compose all the maps from one category to another into a single map.
+    pub(super) fn compose(&self, from: &str, to: &str) -> Option<PiecewiseMap> {
+        let identity = AlmanacMap {
+            source: from.to_string(),
+            destination: from.to_string(),
+            entries: vec![],
+        };
+        Some(
+            self.chain(from, to)?
+                .iter()
+                .fold(identity.normalise(), |composed, map| {
+                    composed.then(&map.normalise())
+                }),
+        )
+    }
+
+    /// The ranges of one category that end up in a range of another category.
+    pub(super) fn preimage_between(
+        &self,
+        from: &str,
+        to: &str,
+        range: Range<u64>,
+    ) -> Option<IntervalSet> {
+        Some(self.compose(from, to)?.preimage(range))
+    }
+
+    /// All the numbers of an earlier category that end up at a number of a later category, going
+    /// back through the maps one by one.
+    pub(super) fn unmap_between(&self, from: &str, to: &str, n: u64) -> Option<Vec<u64>> {
+        let mut numbers = vec![n];
+        for map in self.chain(to, from)?.iter().rev() {
+            numbers = numbers.into_iter().flat_map(|n| map.unmap(n)).collect();
+        }
+        numbers.sort();
+        Some(numbers)
+    }
+
+    /// The lowest location for any of the seed ranges, found by searching up from the lowest
+    /// location instead of mapping the seed ranges forward like `get_locations_from_seed_ranges`.
+    pub(super) fn lowest_location_searching_up(&self) -> Option<u64> {
+        self.compose("seed", "location")?
+            .lowest_from(&self.seed_ranges)
+    }
+}
+
+/// Merge the sorted pieces that follow each other in both their source and destination.
+fn merge(pieces: Vec<AlmanacMapEntry>) -> Vec<AlmanacMapEntry> {
+    let mut merged: Vec<AlmanacMapEntry> = Vec::new();
+    for piece in pieces.into_iter().filter(|piece| !piece.source.is_empty()) {
+        match merged.last_mut() {
+            Some(last)
+                if last.source.end == piece.source.start
+                    && last.destination.end == piece.destination.start =>
+            {
+                last.source.end = piece.source.end;
+                last.destination.end = piece.destination.end;
+            }
+            _ => merged.push(piece),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::example_almanac;
+    use super::super::validate::Precedence;
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn entry(destination: u64, source: u64, size: u64) -> AlmanacMapEntry {
+        AlmanacMapEntry {
+            destination: destination..destination + size,
+            source: source..source + size,
+            overflowed: false,
+        }
+    }
+
+    #[test]
+    fn test_almanac_map_normalise() {
+        let map = AlmanacMap {
+            source: "seed".to_string(),
+            destination: "soil".to_string(),
+            entries: vec![entry(50, 98, 2), entry(52, 50, 48)],
+        };
+
+        // 50..98 and 98..100 follow each other in both source and destination
+        assert_eq!(
+            map.normalise().pieces,
+            vec![
+                entry(0, 0, 50),
+                entry(52, 50, 48),
+                entry(50, 98, 2),
+                entry(100, 100, u64::MAX - 100),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_almanac_map_normalise_overlap() {
+        // the first entry wins where they overlap, 5 and 6 are mapped by it
+        let map = AlmanacMap {
+            source: "a".to_string(),
+            destination: "b".to_string(),
+            entries: vec![entry(100, 5, 2), entry(200, 0, 10)],
+        };
+        let normalised = map.normalise();
+
+        assert_eq!(
+            normalised.pieces,
+            vec![
+                entry(200, 0, 5),
+                entry(100, 5, 2),
+                entry(207, 7, 3),
+                entry(10, 10, u64::MAX - 10),
+            ]
+        );
+        assert_eq!(
+            map.sorted_entries(),
+            vec![entry(200, 0, 5), entry(100, 5, 2), entry(207, 7, 3)]
+        );
+    }
+
+    #[test]
+    fn test_almanac_compose() {
+        let mut almanac = example_almanac();
+        almanac.apply_precedence(Precedence::FirstEntry);
+        let composed = almanac.compose("seed", "location").unwrap();
+
+        for seed in 0..120 {
+            assert_eq!(
+                composed.map(seed),
+                almanac.map_between("seed", "location", seed).unwrap()
+            );
+        }
+        assert!(composed
+            .pieces
+            .windows(2)
+            .all(|pair| pair[0].source.end == pair[1].source.start));
+        assert_eq!(composed.map(u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn test_almanac_compose_display() {
+        let almanac = example_almanac();
+        let composed = almanac.compose("temperature", "location").unwrap();
+        let lines: Vec<String> = composed.to_string().lines().map(String::from).collect();
+
+        assert_eq!(lines[0], "temperature-to-location map:");
+        // temperature 0..55 becomes humidity 1..56, which stays the same as location 1..56
+        assert_eq!(lines[1], "temperature 0..55 -> location 1..56");
+        assert!(lines.last().unwrap().ends_with("(unchanged)"));
+    }
+
+    #[test]
+    fn test_almanac_map_entry_inverse() {
+        let entry = entry(52, 50, 48);
+
+        assert_eq!(entry.inverse().map(&60), Some(58));
+        assert_eq!(entry.inverse().map(&50), None);
+        assert_eq!(entry.inverse().inverse(), entry);
+    }
+
+    #[test]
+    fn test_almanac_map_unmap() {
+        let map = AlmanacMap {
+            source: "seed".to_string(),
+            destination: "soil".to_string(),
+            entries: vec![entry(50, 98, 2), entry(52, 50, 48)],
+        };
+
+        // soil 51 comes from seed 99, and seed 51 maps to 53 instead
+        assert_eq!(map.unmap(51), vec![99]);
+        // soil 98 comes from seed 96, and seed 98 maps to 50 instead
+        assert_eq!(map.unmap(98), vec![96]);
+        // soil 60 comes from seed 58, soil 20 is seed 20 itself
+        assert_eq!(map.unmap(60), vec![58]);
+        assert_eq!(map.unmap(20), vec![20]);
+        assert_eq!(map.unmap(u64::MAX), vec![u64::MAX]);
+    }
+
+    #[test]
+    fn test_almanac_map_unmap_many() {
+        // 3 is mapped by the entry, and 13 maps to itself as well; 5 is taken by the first entry
+        let map = AlmanacMap {
+            source: "a".to_string(),
+            destination: "b".to_string(),
+            entries: vec![entry(50, 5, 1), entry(10, 0, 10)],
+        };
+
+        assert_eq!(map.unmap(13), vec![3, 13]);
+        assert_eq!(map.unmap(15), vec![15]);
+        assert_eq!(map.unmap(5), vec![]);
+    }
+
+    #[test]
+    fn test_almanac_preimage_between() {
+        let mut almanac = example_almanac();
+        almanac.apply_precedence(Precedence::FirstEntry);
+
+        // seed 79 ends up at location 82, seed 14 at 43
+        let seeds = almanac
+            .preimage_between("seed", "location", 82..83)
+            .unwrap();
+        assert!(seeds.contains(79));
+        let seeds = almanac
+            .preimage_between("seed", "location", 40..50)
+            .unwrap();
+        for seed in seeds.ranges().iter().flat_map(|range| range.clone()) {
+            assert!((40..50).contains(&almanac.map_between("seed", "location", seed).unwrap()));
+        }
+        assert!(seeds.contains(14));
+        assert_eq!(almanac.preimage_between("location", "seed", 0..1), None);
+    }
+
+    #[test]
+    fn test_almanac_lowest_location_searching_up() {
+        let almanac = example_almanac();
+
+        assert_eq!(almanac.lowest_location_searching_up(), Some(46));
+        assert_eq!(
+            almanac.lowest_location_searching_up(),
+            almanac.get_locations_from_seed_ranges().first()
+        );
+    }
+
+    #[test]
+    fn test_almanac_unmap_between() {
+        let almanac = example_almanac();
+
+        assert_eq!(
+            almanac.unmap_between("location", "seed", 82),
+            Some(vec![79])
+        );
+        assert_eq!(
+            almanac.unmap_between("humidity", "soil", 78),
+            Some(vec![81])
+        );
+        assert_eq!(almanac.unmap_between("seed", "location", 79), None);
+    }
+}
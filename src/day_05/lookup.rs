@@ -0,0 +1,228 @@
+/// Different ways to look up the locations of the seeds, and a benchmark to compare them on random
+/// almanacs with many more entries than the puzzle's.
+use super::{Almanac, AlmanacMap, AlmanacMapEntry};
+use crate::interval_set::IntervalSet;
+use crate::random::XorShift;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Lookup {
+    /// Go through the entries of every map one by one for every seed.
+    Linear,
+    /// Find the entry for every seed with a binary search through the sorted entries of every map.
+    BinarySearch,
+    /// Sort the seeds, then map all the seeds through every map in a single pass along its sorted
+    /// entries.
+    Batch,
+}
+
+impl Almanac {
+    /// The location of every seed, in the order of the seeds. The entries of the maps have to be
+    /// sorted, like `apply_precedence` leaves them.
+    pub(super) fn get_locations_with(&self, lookup: Lookup) -> Vec<u64> {
+        let chain = self
+            .chain("seed", "location")
+            .expect("there are no maps from seed to location");
+        match lookup {
+            Lookup::Linear => self
+                .seeds
+                .iter()
+                .map(|&seed| {
+                    chain.iter().fold(seed, |n, map| {
+                        map.entries
+                            .iter()
+                            .find_map(|entry| entry.map(&n))
+                            .unwrap_or(n)
+                    })
+                })
+                .collect(),
+            Lookup::BinarySearch => self
+                .seeds
+                .iter()
+                .map(|&seed| chain.iter().fold(seed, |n, map| map.map(n)))
+                .collect(),
+            Lookup::Batch => {
+                // keep track of where every seed came from, to put the locations back in order
+                let mut numbers: Vec<(u64, usize)> = self
+                    .seeds
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &seed)| (seed, i))
+                    .collect();
+                for map in chain.iter() {
+                    numbers.sort_unstable();
+                    let sorted: Vec<u64> = numbers.iter().map(|&(n, _)| n).collect();
+                    for (number, mapped) in numbers.iter_mut().zip(map.map_sorted(&sorted)) {
+                        number.0 = mapped;
+                    }
+                }
+                numbers.sort_unstable_by_key(|&(_, i)| i);
+                numbers.into_iter().map(|(n, _)| n).collect()
+            }
+        }
+    }
+}
+
+impl AlmanacMap {
+    /// Map sorted numbers in a single pass, walking through the sorted entries along with the
+    /// numbers.
+    fn map_sorted(&self, numbers: &[u64]) -> Vec<u64> {
+        debug_assert!(numbers.windows(2).all(|pair| pair[0] <= pair[1]));
+        let mut entries = self.entries.iter().peekable();
+        numbers
+            .iter()
+            .map(|&n| {
+                // the entries ending before the number can't map any of the later numbers either
+                while entries.next_if(|entry| entry.source.end <= n).is_some() {}
+                entries.peek().and_then(|entry| entry.map(&n)).unwrap_or(n)
+            })
+            .collect()
+    }
+}
+
+/// Generate an almanac with `seeds` seeds and `entries` entries in each of its seven maps. The
+/// entries of a map cut up the numbers below 2^32 and shuffle the pieces around, so their sources
+/// don't overlap and neither do their destinations, like in the puzzle. The entries are sorted by
+/// their source, the way `apply_precedence` leaves them.
+pub(super) fn random_almanac(rng: &mut XorShift, entries: usize, seeds: usize) -> Almanac {
+    const CATEGORIES: [&str; 8] = [
+        "seed",
+        "soil",
+        "fertilizer",
+        "water",
+        "light",
+        "temperature",
+        "humidity",
+        "location",
+    ];
+    const END: u64 = 1 << 32;
+
+    let maps = CATEGORIES
+        .windows(2)
+        .map(|pair| {
+            let mut cuts: Vec<u64> = (1..entries).map(|_| rng.between(0, END)).collect();
+            cuts.extend([0, END]);
+            cuts.sort();
+            let mut sources: Vec<std::ops::Range<u64>> =
+                cuts.windows(2).map(|cut| cut[0]..cut[1]).collect();
+
+            // lay the pieces out again in a shuffled order for their destinations
+            let mut order: Vec<usize> = (0..sources.len()).collect();
+            for i in (1..order.len()).rev() {
+                order.swap(i, rng.between(0, i as u64) as usize);
+            }
+            let mut start = 0;
+            let mut map_entries = Vec::new();
+            for i in order {
+                let source = std::mem::take(&mut sources[i]);
+                let size = source.end - source.start;
+                map_entries.push(AlmanacMapEntry {
+                    destination: start..start + size,
+                    source,
+                    overflowed: false,
+                });
+                start += size;
+            }
+            map_entries.sort_by_key(|entry| entry.source.start);
+
+            AlmanacMap {
+                source: pair[0].to_string(),
+                destination: pair[1].to_string(),
+                entries: map_entries,
+            }
+        })
+        .collect();
+
+    Almanac {
+        seeds: (0..seeds).map(|_| rng.between(0, END - 1)).collect(),
+        seed_ranges: IntervalSet::new(),
+        maps,
+    }
+}
+
+/// Time every way to look up the locations of the seeds, checking they all find the same.
+pub(super) fn benchmark(almanac: &Almanac) -> Vec<(Lookup, Duration)> {
+    let mut expected = None;
+    [Lookup::Linear, Lookup::BinarySearch, Lookup::Batch]
+        .into_iter()
+        .map(|lookup| {
+            let start = Instant::now();
+            let locations = almanac.get_locations_with(lookup);
+            let duration = start.elapsed();
+            assert_eq!(
+                *expected.get_or_insert_with(|| locations.clone()),
+                locations,
+                "{:?} found other locations",
+                lookup
+            );
+            (lookup, duration)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::example_almanac;
+    use super::super::validate::Precedence;
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_get_locations_with() {
+        let mut almanac = example_almanac();
+        almanac.apply_precedence(Precedence::FirstEntry);
+
+        for lookup in [Lookup::Linear, Lookup::BinarySearch, Lookup::Batch] {
+            assert_eq!(almanac.get_locations_with(lookup), vec![82, 43, 86, 35]);
+        }
+    }
+
+    #[test]
+    fn test_almanac_map_map_sorted() {
+        let mut almanac = example_almanac();
+        almanac.apply_precedence(Precedence::FirstEntry);
+        let numbers: Vec<u64> = (0..120).chain([u64::MAX - 1, u64::MAX]).collect();
+
+        for map in almanac.maps.iter() {
+            let expected: Vec<u64> = numbers.iter().map(|&n| map.map(n)).collect();
+            assert_eq!(map.map_sorted(&numbers), expected);
+            assert_eq!(map.map_sorted(&[]), vec![]);
+        }
+    }
+
+    #[test]
+    fn test_random_almanac() {
+        let almanac = random_almanac(&mut XorShift::new(50), 100, 20);
+
+        assert_eq!(almanac.maps.len(), 7);
+        assert_eq!(almanac.seeds.len(), 20);
+        for map in almanac.maps.iter() {
+            assert_eq!(map.entries.len(), 100);
+            assert!(map
+                .entries
+                .windows(2)
+                .all(|pair| pair[0].source.end == pair[1].source.start));
+            let sources: IntervalSet = map.entries.iter().map(|e| e.source.clone()).collect();
+            let destinations: IntervalSet =
+                map.entries.iter().map(|e| e.destination.clone()).collect();
+            assert_eq!(sources, IntervalSet::from(0..1 << 32));
+            assert_eq!(destinations, IntervalSet::from(0..1 << 32));
+        }
+    }
+
+    #[test]
+    fn test_benchmark() {
+        let almanac = random_almanac(&mut XorShift::new(50), 300, 200);
+        let timings = benchmark(&almanac);
+
+        let lookups: Vec<Lookup> = timings.iter().map(|&(lookup, _)| lookup).collect();
+        assert_eq!(
+            lookups,
+            vec![Lookup::Linear, Lookup::BinarySearch, Lookup::Batch]
+        );
+        assert_eq!(
+            almanac.get_locations(),
+            almanac.get_locations_with(Lookup::Linear)
+        );
+    }
+}
@@ -0,0 +1,251 @@
+/// Check the maps of the almanac for entries that get in each other's way: entries with
+/// overlapping sources (only one of them can be used for those numbers), entries with overlapping
+/// destinations, entries without any numbers, and entries going past the highest number.
+use super::{Almanac, AlmanacMapEntry};
+use std::fmt;
+use std::ops::Range;
+
+#[derive(Debug, PartialEq)]
+pub(super) enum MapProblem {
+    OverlappingSources {
+        map: String,
+        first: usize,
+        second: usize,
+        overlap: Range<u64>,
+    },
+    OverlappingDestinations {
+        map: String,
+        first: usize,
+        second: usize,
+        overlap: Range<u64>,
+    },
+    ZeroLength {
+        map: String,
+        entry: usize,
+    },
+    /// The start plus the size of the entry goes past `u64::MAX`.
+    Overflow {
+        map: String,
+        entry: usize,
+    },
+}
+
+impl fmt::Display for MapProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // the entries are counted from 1, like the lines under the map's header
+        match self {
+            MapProblem::OverlappingSources {
+                map,
+                first,
+                second,
+                overlap,
+            } => write!(
+                f,
+                "entries {} and {} of the {} map both map {:?}",
+                first + 1,
+                second + 1,
+                map,
+                overlap
+            ),
+            MapProblem::OverlappingDestinations {
+                map,
+                first,
+                second,
+                overlap,
+            } => write!(
+                f,
+                "entries {} and {} of the {} map both map onto {:?}",
+                first + 1,
+                second + 1,
+                map,
+                overlap
+            ),
+            MapProblem::ZeroLength { map, entry } => {
+                write!(f, "entry {} of the {} map is empty", entry + 1, map)
+            }
+            MapProblem::Overflow { map, entry } => write!(
+                f,
+                "entry {} of the {} map goes past {}",
+                entry + 1,
+                map,
+                u64::MAX
+            ),
+        }
+    }
+}
+
+/// Which entry of a map is used for the numbers in more than one entry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Precedence {
+    FirstEntry,
+    LastEntry,
+}
+
+impl Almanac {
+    /// Cut the entries of every map down to the numbers they're used for, where they overlap the
+    /// entry that takes precedence keeps the numbers. The entries are stored sorted by their source
+    /// after that, which is how the maps look numbers up.
+    pub(super) fn apply_precedence(&mut self, precedence: Precedence) {
+        for map in self.maps.iter_mut() {
+            if precedence == Precedence::LastEntry {
+                map.entries.reverse();
+            }
+            map.entries = map.sorted_entries();
+        }
+    }
+}
+
+/// Find the problems with the maps of the almanac, map by map and entry by entry. The entries going
+/// past the highest number are cut short, those are still checked for overlaps the way they're
+/// used.
+pub(super) fn validate_maps(almanac: &Almanac) -> Vec<MapProblem> {
+    let mut problems = Vec::new();
+
+    for map in almanac.maps.iter() {
+        let name = format!("{}-to-{}", map.source, map.destination);
+        let mut entries: Vec<(usize, &AlmanacMapEntry)> = Vec::new();
+        for (entry, almanac_entry) in map.entries.iter().enumerate() {
+            let map = name.clone();
+            if almanac_entry.overflowed {
+                problems.push(MapProblem::Overflow { map, entry });
+            } else if almanac_entry.source.is_empty() {
+                problems.push(MapProblem::ZeroLength { map, entry });
+            }
+            if !almanac_entry.source.is_empty() {
+                entries.push((entry, almanac_entry));
+            }
+        }
+
+        for (i, (first, a)) in entries.iter().enumerate() {
+            for (second, b) in entries[i + 1..].iter() {
+                let (first, second, map) = (*first, *second, name.clone());
+                if let Some(overlap) = overlap(&a.source, &b.source) {
+                    problems.push(MapProblem::OverlappingSources {
+                        map: map.clone(),
+                        first,
+                        second,
+                        overlap,
+                    });
+                }
+                if let Some(overlap) = overlap(&a.destination, &b.destination) {
+                    problems.push(MapProblem::OverlappingDestinations {
+                        map,
+                        first,
+                        second,
+                        overlap,
+                    });
+                }
+            }
+        }
+    }
+
+    problems
+}
+
+fn overlap(a: &Range<u64>, b: &Range<u64>) -> Option<Range<u64>> {
+    let overlap = a.start.max(b.start)..a.end.min(b.end);
+    (!overlap.is_empty()).then_some(overlap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const INPUT: &str = "\
+        seeds: 79 14 55 13\n\
+        \n\
+        seed-to-soil map:\n\
+        50 98 2\n\
+        52 50 48\n\
+        \n\
+        soil-to-location map:\n\
+        0 15 37\n\
+        100 20 10\n\
+        30 0 0\n\
+        18446744073709551610 60 10\n\
+        20 18446744073709551610 10";
+
+    fn validate_input(input: &str) -> Vec<MapProblem> {
+        let (_, almanac) = Almanac::parse(input).unwrap();
+        validate_maps(&almanac)
+    }
+
+    #[test]
+    fn test_validate_maps() {
+        assert_eq!(
+            validate_input(INPUT),
+            vec![
+                MapProblem::ZeroLength {
+                    map: "soil-to-location".to_string(),
+                    entry: 2
+                },
+                MapProblem::Overflow {
+                    map: "soil-to-location".to_string(),
+                    entry: 3
+                },
+                MapProblem::Overflow {
+                    map: "soil-to-location".to_string(),
+                    entry: 4
+                },
+                MapProblem::OverlappingSources {
+                    map: "soil-to-location".to_string(),
+                    first: 0,
+                    second: 1,
+                    overlap: 20..30
+                },
+                // the overflowing entry is cut short, but still maps onto the first entry's numbers
+                MapProblem::OverlappingDestinations {
+                    map: "soil-to-location".to_string(),
+                    first: 0,
+                    second: 4,
+                    overlap: 20..25
+                },
+            ]
+        );
+        assert_eq!(
+            validate_input(INPUT)[3].to_string(),
+            "entries 1 and 2 of the soil-to-location map both map 20..30"
+        );
+    }
+
+    #[test]
+    fn test_validate_maps_destinations() {
+        let input = "seeds: 1\n\na-to-b map:\n10 0 5\n12 20 5";
+
+        assert_eq!(
+            validate_input(input),
+            vec![MapProblem::OverlappingDestinations {
+                map: "a-to-b".to_string(),
+                first: 0,
+                second: 1,
+                overlap: 12..15
+            }]
+        );
+    }
+
+    #[test]
+    fn test_apply_precedence() {
+        let (_, almanac) = Almanac::parse(INPUT).unwrap();
+        // the overflowing entries are cut short at the highest number
+        assert_eq!(almanac.maps[1].entries[3].source, 60..65);
+        assert!(almanac.maps[1].entries[3].overflowed);
+        assert!(!almanac.maps[1].entries[2].overflowed);
+
+        let (_, mut first) = Almanac::parse(INPUT).unwrap();
+        first.apply_precedence(Precedence::FirstEntry);
+        assert_eq!(first.map_between("soil", "location", 25), Some(10));
+        // the second entry is taken over by the first one, and the empty one is dropped
+        let sources: Vec<_> = first.maps[1]
+            .entries
+            .iter()
+            .map(|entry| entry.source.clone())
+            .collect();
+        assert_eq!(sources, vec![15..52, 60..65, u64::MAX - 5..u64::MAX]);
+
+        let (_, mut last) = Almanac::parse(INPUT).unwrap();
+        last.apply_precedence(Precedence::LastEntry);
+        assert_eq!(last.map_between("soil", "location", 25), Some(105));
+        assert_eq!(last.map_between("soil", "location", 17), Some(2));
+    }
+}
@@ -97,7 +97,7 @@
 /// How many ways can you beat the record in this one much longer race?
 use std::ops::RangeInclusive;
 
-pub fn run() {
+pub fn run(_options: &[String]) {
     // small input, so hardcode instead of parsing
     // Time:        53     89     76     98
     // Distance:   313   1090   1214   1201
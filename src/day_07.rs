@@ -131,7 +131,7 @@ use std::collections::HashMap;
 
 const INPUT: &str = include_str!("../input/day_07");
 
-pub fn run() {
+pub fn run(_options: &[String]) {
     let (_, hands) = separated_list1(newline, Hand::parse)(INPUT).expect("Parsing went wrong");
 
     let mut valued_hands: Vec<_> = hands.clone().into_iter().map(ValuedHand::from).collect();
@@ -0,0 +1,240 @@
+/// A set of numbers kept as ranges, for sets too big to hold every number in. The ranges are
+/// always normalised: sorted, not empty, and never overlapping or following each other.
+use std::ops::Range;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntervalSet {
+    ranges: Vec<Range<u64>>,
+}
+
+impl From<Range<u64>> for IntervalSet {
+    fn from(range: Range<u64>) -> IntervalSet {
+        IntervalSet::from_iter([range])
+    }
+}
+
+impl FromIterator<Range<u64>> for IntervalSet {
+    fn from_iter<I: IntoIterator<Item = Range<u64>>>(iter: I) -> IntervalSet {
+        let mut ranges: Vec<Range<u64>> = iter.into_iter().filter(|r| !r.is_empty()).collect();
+        ranges.sort_by_key(|range| range.start);
+
+        // merge the ranges that overlap or follow each other
+        let mut normalised: Vec<Range<u64>> = Vec::new();
+        for range in ranges {
+            match normalised.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => normalised.push(range),
+            }
+        }
+        IntervalSet { ranges: normalised }
+    }
+}
+
+impl IntervalSet {
+    pub fn new() -> IntervalSet {
+        IntervalSet::default()
+    }
+
+    /// The sorted ranges of the set.
+    pub fn ranges(&self) -> &[Range<u64>] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// The lowest number in the set.
+    pub fn first(&self) -> Option<u64> {
+        self.ranges.first().map(|range| range.start)
+    }
+
+    #[allow(dead_code)]
+    pub fn contains(&self, n: u64) -> bool {
+        let i = self.ranges.partition_point(|range| range.end <= n);
+        self.ranges.get(i).is_some_and(|range| range.contains(&n))
+    }
+
+    /// The amount of numbers in the set.
+    pub fn len(&self) -> u64 {
+        self.ranges
+            .iter()
+            .map(|range| range.end - range.start)
+            .sum()
+    }
+
+    pub fn union(&self, other: &IntervalSet) -> IntervalSet {
+        self.ranges
+            .iter()
+            .chain(other.ranges.iter())
+            .cloned()
+            .collect()
+    }
+
+    pub fn intersection(&self, other: &IntervalSet) -> IntervalSet {
+        // walk through both sorted lists of ranges together
+        let (mut i, mut j) = (0, 0);
+        let mut ranges = Vec::new();
+        while let (Some(a), Some(b)) = (self.ranges.get(i), other.ranges.get(j)) {
+            let overlap = a.start.max(b.start)..a.end.min(b.end);
+            if !overlap.is_empty() {
+                ranges.push(overlap);
+            }
+            // the range ending first can't overlap with anything else anymore
+            match a.end <= b.end {
+                true => i += 1,
+                false => j += 1,
+            }
+        }
+        IntervalSet { ranges }
+    }
+
+    /// The numbers in this set that aren't in the other set.
+    pub fn difference(&self, other: &IntervalSet) -> IntervalSet {
+        let mut ranges = Vec::new();
+        for range in self.ranges.iter() {
+            let mut start = range.start;
+            // skip the other ranges ending before this one starts, they don't take anything out
+            let first = other.ranges.partition_point(|other| other.end <= start);
+            for other in other.ranges[first..].iter() {
+                if other.start >= range.end {
+                    break;
+                }
+                if start < other.start {
+                    ranges.push(start..other.start);
+                }
+                start = start.max(other.end);
+            }
+            if start < range.end {
+                ranges.push(start..range.end);
+            }
+        }
+        IntervalSet { ranges }
+    }
+
+    /// Add the range to the set, merging it with the ranges it overlaps or follows.
+    pub fn insert(&mut self, range: Range<u64>) {
+        if range.is_empty() {
+            return;
+        }
+        // the ranges from the first one ending at or after the start up to the last one starting
+        // at or before the end are merged with the range
+        let first = self.ranges.partition_point(|other| other.end < range.start);
+        let last = self
+            .ranges
+            .partition_point(|other| other.start <= range.end);
+        let merged = match self.ranges[first..last] {
+            [] => range,
+            ref overlapping => {
+                overlapping[0].start.min(range.start)
+                    ..overlapping[overlapping.len() - 1].end.max(range.end)
+            }
+        };
+        self.ranges.splice(first..last, [merged]);
+    }
+
+    /// Move every number in the set by the offset.
+    ///
+    /// Panics when a number ends up below 0 or above `u64::MAX`.
+    pub fn translate(&self, offset: i128) -> IntervalSet {
+        let translate = |n: u64| {
+            u64::try_from(n as i128 + offset).expect("the set was translated out of range")
+        };
+        IntervalSet {
+            ranges: self
+                .ranges
+                .iter()
+                .map(|range| translate(range.start)..translate(range.end))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval_set_normalise() {
+        let set = IntervalSet::from_iter([10..20, 5..8, 15..25, 25..30, 40..40, 8..9]);
+
+        assert_eq!(set.ranges(), &[5..9, 10..30]);
+        assert_eq!(set.len(), 24);
+        assert_eq!(set.first(), Some(5));
+        assert!(IntervalSet::from(3..3).is_empty());
+    }
+
+    #[test]
+    fn test_interval_set_contains() {
+        let set = IntervalSet::from_iter([5..9, 10..30]);
+
+        assert!(set.contains(5));
+        assert!(set.contains(29));
+        assert!(!set.contains(9));
+        assert!(!set.contains(30));
+        assert!(!set.contains(0));
+    }
+
+    #[test]
+    fn test_interval_set_union() {
+        let a = IntervalSet::from_iter([0..5, 20..30]);
+        let b = IntervalSet::from_iter([5..10, 25..40, 50..60]);
+
+        assert_eq!(a.union(&b).ranges(), &[0..10, 20..40, 50..60]);
+        assert_eq!(a.union(&IntervalSet::new()), a);
+    }
+
+    #[test]
+    fn test_interval_set_intersection() {
+        let a = IntervalSet::from_iter([0..10, 20..30, 40..50]);
+        let b = IntervalSet::from_iter([5..25, 28..45]);
+
+        assert_eq!(
+            a.intersection(&b).ranges(),
+            &[5..10, 20..25, 28..30, 40..45]
+        );
+        assert_eq!(a.intersection(&b), b.intersection(&a));
+        assert!(a.intersection(&IntervalSet::from(10..20)).is_empty());
+    }
+
+    #[test]
+    fn test_interval_set_difference() {
+        let a = IntervalSet::from_iter([0..10, 20..30, 40..50]);
+        let b = IntervalSet::from_iter([5..25, 28..29, 45..60]);
+
+        assert_eq!(a.difference(&b).ranges(), &[0..5, 25..28, 29..30, 40..45]);
+        assert_eq!(b.difference(&a).ranges(), &[10..20, 50..60]);
+        assert!(a.difference(&a).is_empty());
+        assert_eq!(a.difference(&IntervalSet::new()), a);
+    }
+
+    #[test]
+    fn test_interval_set_insert() {
+        let mut set = IntervalSet::from_iter([5..9, 20..30]);
+
+        set.insert(0..2);
+        assert_eq!(set.ranges(), &[0..2, 5..9, 20..30]);
+        set.insert(9..12);
+        assert_eq!(set.ranges(), &[0..2, 5..12, 20..30]);
+        set.insert(15..15);
+        assert_eq!(set.ranges(), &[0..2, 5..12, 20..30]);
+        set.insert(1..25);
+        assert_eq!(set, IntervalSet::from(0..30));
+        set.insert(40..50);
+        assert_eq!(set, IntervalSet::from_iter([0..30, 40..50]));
+    }
+
+    #[test]
+    fn test_interval_set_translate() {
+        let set = IntervalSet::from_iter([5..9, 10..30]);
+
+        assert_eq!(set.translate(-5).ranges(), &[0..4, 5..25]);
+        assert_eq!(set.translate(100).ranges(), &[105..109, 110..130]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_interval_set_translate_below_zero() {
+        IntervalSet::from(5..9).translate(-6);
+    }
+}
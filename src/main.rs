@@ -3,17 +3,41 @@ extern crate lazy_static;
 use std::collections::HashMap;
 use std::env;
 
+mod interval_set;
+mod random;
+
 fn main() {
     let mut args = env::args();
     args.next();
 
     let modules = create_modules();
-    while let Some(arg) = args.next() {
-        modules.run(&arg);
+
+    // every module name is followed by the options meant for that module,
+    // e.g. `day_01 --unicode day_02`
+    let mut runs: Vec<(String, Vec<String>)> = Vec::new();
+    for arg in args {
+        match (modules.contains(&arg), runs.last_mut()) {
+            (true, _) => runs.push((arg, Vec::new())),
+            (false, Some((_, options))) => options.push(arg),
+            (false, None) => panic!("unknown module: {}", arg),
+        }
+    }
+
+    for (name, options) in runs.iter() {
+        modules.run(name, options);
     }
 }
 
-type Run = fn();
+type Run = fn(&[String]);
+
+/// Find the value following an option, e.g. `--bag red=12` gives `red=12` for `--bag`.
+fn option_value<'a>(options: &'a [String], name: &str) -> Option<&'a str> {
+    options
+        .iter()
+        .position(|option| option == name)
+        .and_then(|i| options.get(i + 1))
+        .map(String::as_str)
+}
 
 struct Modules {
     runners: HashMap<String, Run>,
@@ -24,9 +48,13 @@ impl Modules {
         self.runners.insert(name, func);
     }
 
-    fn run(&self, name: &String) {
+    fn contains(&self, name: &str) -> bool {
+        self.runners.contains_key(name)
+    }
+
+    fn run(&self, name: &str, options: &[String]) {
         let runner = self.runners.get(name).unwrap();
-        runner();
+        runner(options);
     }
 }
 